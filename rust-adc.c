@@ -0,0 +1,39 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+#include <zephyr.h>
+#include <version.h>
+#include <drivers/adc.h>
+
+#if ZEPHYR_VERSION_CODE >= ZEPHYR_VERSION(2, 4, 0)
+#define RUST_ADC_DEVICE const struct device
+#else
+#define RUST_ADC_DEVICE struct device
+#endif
+
+/* channel_id and differential are bitfields, and the inputs depend on config */
+void rust_adc_channel_cfg_init(struct adc_channel_cfg *cfg,
+			       enum adc_gain gain,
+			       enum adc_reference reference,
+			       uint16_t acquisition_time,
+			       uint8_t channel_id, bool differential,
+			       uint8_t input_positive, uint8_t input_negative)
+{
+	*cfg = (struct adc_channel_cfg){
+		.gain = gain,
+		.reference = reference,
+		.acquisition_time = acquisition_time,
+		.channel_id = channel_id,
+		.differential = differential,
+	};
+#ifdef CONFIG_ADC_CONFIGURABLE_INPUTS
+	cfg->input_positive = input_positive;
+	cfg->input_negative = input_negative;
+#endif
+}
+
+uint16_t rust_adc_ref_internal(RUST_ADC_DEVICE *dev)
+{
+	return adc_ref_internal(dev);
+}
@@ -0,0 +1,33 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+#include <zephyr.h>
+#include <version.h>
+#include <drivers/gpio.h>
+
+/*
+ * gpio_init_callback, gpio_add_callback and gpio_remove_callback are inline,
+ * so are not in the Rust bindings.
+ */
+
+#if ZEPHYR_VERSION_CODE >= ZEPHYR_VERSION(2, 4, 0)
+#define RUST_GPIO_DEVICE const struct device
+#else
+#define RUST_GPIO_DEVICE struct device
+#endif
+
+int rust_gpio_add_callback(RUST_GPIO_DEVICE *port,
+			   struct gpio_callback *callback,
+			   gpio_callback_handler_t handler,
+			   gpio_port_pins_t pin_mask)
+{
+	gpio_init_callback(callback, handler, pin_mask);
+	return gpio_add_callback(port, callback);
+}
+
+int rust_gpio_remove_callback(RUST_GPIO_DEVICE *port,
+			      struct gpio_callback *callback)
+{
+	return gpio_remove_callback(port, callback);
+}
@@ -7,6 +7,16 @@
 struct k_mem_domain rust_std_domain;
 K_APPMEM_PARTITION_DEFINE(rust_std_partition);
 #define RUST_STD_SECTION K_APP_DMEM_SECTION(rust_std_partition)
+
+/* Partitions declared with k_appmem_partition_define! */
+const k_mem_partition_attr_t rust_appmem_partition_attr =
+	K_MEM_PARTITION_P_RW_U_RW;
+
+/* k_is_user_context is inline, so not callable from Rust */
+bool rust_is_user_context(void)
+{
+	return k_is_user_context();
+}
 #else
 #define RUST_STD_SECTION .data
 #endif
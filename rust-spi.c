@@ -0,0 +1,32 @@
+/*
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+#include <zephyr.h>
+#include <version.h>
+#include <drivers/spi.h>
+
+#if ZEPHYR_VERSION_CODE >= ZEPHYR_VERSION(2, 4, 0)
+#define RUST_SPI_DEVICE const struct device
+#else
+#define RUST_SPI_DEVICE struct device
+#endif
+
+/* The layout of spi_cs_control differs between kernel versions */
+void rust_spi_cs_control_init(struct spi_cs_control *cs,
+			      RUST_SPI_DEVICE *port, gpio_pin_t pin,
+			      uint32_t dt_flags, uint32_t delay)
+{
+#if ZEPHYR_VERSION_CODE >= ZEPHYR_VERSION(2, 7, 0)
+	cs->gpio.port = port;
+	cs->gpio.pin = pin;
+	cs->gpio.dt_flags = dt_flags;
+#else
+	cs->gpio_dev = port;
+	cs->gpio_pin = pin;
+#if ZEPHYR_VERSION_CODE >= ZEPHYR_VERSION(2, 4, 0)
+	cs->gpio_dt_flags = dt_flags;
+#endif
+#endif
+	cs->delay = delay;
+}
@@ -0,0 +1,214 @@
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const BITS: usize = usize::BITS as usize;
+
+/// Number of words needed for a bitmap of `len` bits
+pub const fn words_for(len: usize) -> usize {
+    (len + BITS - 1) / BITS
+}
+
+/// Lock-free allocator of the indices `0..len`, backed by a bitmap of `W` words
+///
+/// A set bit is allocated. Bits past `len` in the last word start out set so they are never
+/// handed out. Allocation finds a free bit with a trailing zero count, starting from the word that
+/// last changed, so it is O(1) unless the bitmap is nearly full.
+pub struct BitmapAlloc<const W: usize> {
+    words: [AtomicUsize; W],
+    len: usize,
+    hint: AtomicUsize,
+}
+
+impl<const W: usize> BitmapAlloc<W> {
+    /// `W` must be `words_for(len)`
+    pub const fn new(len: usize) -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const FREE: AtomicUsize = AtomicUsize::new(0);
+
+        assert!(W == words_for(len));
+        let mut words = [FREE; W];
+        let tail = len % BITS;
+        if tail != 0 {
+            words[W - 1] = AtomicUsize::new(!0 << tail);
+        }
+        BitmapAlloc {
+            words,
+            len,
+            hint: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Allocate the lowest free index in the first word with any free. None if all are allocated.
+    pub fn alloc(&self) -> Option<usize> {
+        let start = self.hint.load(Ordering::Relaxed);
+        for n in 0..W {
+            let i = (start + n) % W;
+            let word = &self.words[i];
+            let mut cur = word.load(Ordering::Relaxed);
+            while cur != !0 {
+                let bit = (!cur).trailing_zeros() as usize;
+                match word.compare_exchange_weak(
+                    cur,
+                    cur | (1 << bit),
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        self.hint.store(i, Ordering::Relaxed);
+                        return Some(i * BITS + bit);
+                    }
+                    Err(actual) => cur = actual,
+                }
+            }
+        }
+        None
+    }
+
+    /// Free an allocated index. Returns false if it was not allocated, i.e. a double free.
+    ///
+    /// Panics if `index` is out of range.
+    pub fn free(&self, index: usize) -> bool {
+        assert!(index < self.len, "index out of range");
+        let i = index / BITS;
+        let mask = 1 << (index % BITS);
+        let prev = self.words[i].fetch_and(!mask, Ordering::Release);
+        self.hint.store(i, Ordering::Relaxed);
+        prev & mask != 0
+    }
+
+    pub fn is_allocated(&self, index: usize) -> bool {
+        index < self.len
+            && self.words[index / BITS].load(Ordering::Relaxed) & (1 << (index % BITS)) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl<const W: usize> BitmapAlloc<W> {
+        /// For tests with a fixed `W` covering several lengths. Unused trailing words are full.
+        fn new_checked(len: usize) -> Self {
+            assert!(words_for(len) <= W);
+            let bitmap = BitmapAlloc {
+                words: [(); W].map(|_| AtomicUsize::new(!0)),
+                len,
+                hint: AtomicUsize::new(0),
+            };
+            for i in 0..len {
+                bitmap.words[i / BITS].fetch_and(!(1 << (i % BITS)), Ordering::Relaxed);
+            }
+            bitmap
+        }
+    }
+
+    #[test]
+    fn test_exhaust() {
+        for &len in &[1, 7, 8, BITS - 1, BITS, BITS + 1, 3 * BITS + 5] {
+            let mut seen = std::vec![false; len];
+            let bitmap = std::boxed::Box::new(BitmapAlloc::<4>::new_checked(len));
+            for _ in 0..len {
+                let i = bitmap.alloc().expect("alloc");
+                assert!(i < len, "{} out of range for {}", i, len);
+                assert!(!seen[i], "{} allocated twice", i);
+                seen[i] = true;
+            }
+            assert_eq!(bitmap.alloc(), None);
+        }
+    }
+
+    #[test]
+    fn test_partial_last_word() {
+        static BITMAP: BitmapAlloc<{ words_for(70) }> = BitmapAlloc::new(70);
+        let mut count = 0;
+        while let Some(i) = BITMAP.alloc() {
+            assert!(i < 70);
+            count += 1;
+        }
+        assert_eq!(count, 70);
+    }
+
+    #[test]
+    fn test_empty() {
+        static BITMAP: BitmapAlloc<0> = BitmapAlloc::new(0);
+        assert!(BITMAP.is_empty());
+        assert_eq!(BITMAP.alloc(), None);
+    }
+
+    #[test]
+    fn test_free_and_reuse() {
+        let bitmap: BitmapAlloc<{ words_for(100) }> = BitmapAlloc::new(100);
+        while bitmap.alloc().is_some() {}
+        assert!(bitmap.free(42));
+        assert!(bitmap.free(99));
+        assert!(!bitmap.is_allocated(42));
+        let mut again = [bitmap.alloc().unwrap(), bitmap.alloc().unwrap()];
+        again.sort_unstable();
+        assert_eq!(again, [42, 99]);
+        assert_eq!(bitmap.alloc(), None);
+    }
+
+    #[test]
+    fn test_double_free() {
+        let bitmap: BitmapAlloc<{ words_for(10) }> = BitmapAlloc::new(10);
+        let i = bitmap.alloc().unwrap();
+        assert!(bitmap.free(i));
+        assert!(!bitmap.free(i));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_free_out_of_range() {
+        let bitmap: BitmapAlloc<{ words_for(10) }> = BitmapAlloc::new(10);
+        bitmap.free(10);
+    }
+
+    #[test]
+    fn test_concurrent() {
+        use std::sync::Arc;
+        use std::vec::Vec;
+
+        const LEN: usize = 200;
+        let bitmap = Arc::new(BitmapAlloc::<{ words_for(LEN) }>::new(LEN));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let bitmap = bitmap.clone();
+                std::thread::spawn(move || {
+                    let mut mine = Vec::new();
+                    for _ in 0..1000 {
+                        if let Some(i) = bitmap.alloc() {
+                            mine.push(i);
+                        }
+                        if mine.len() > 10 {
+                            assert!(bitmap.free(mine.remove(0)));
+                        }
+                    }
+                    mine
+                })
+            })
+            .collect();
+        let mut all: Vec<usize> = threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect();
+        let count = all.len();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), count, "index held by two threads");
+        for i in all {
+            assert!(bitmap.is_allocated(i));
+        }
+    }
+}
@@ -9,10 +9,26 @@ fn main() {
         println!("cargo:rustc-cfg=zephyr250");
     }
 
+    if kernel_version >= 0x2_06_00 {
+        println!("cargo:rustc-cfg=zephyr260");
+    }
+
     if kernel_version >= 0x2_07_00 {
         println!("cargo:rustc-cfg=zephyr270");
     }
 
+    if kernel_version >= 0x3_00_00 {
+        println!("cargo:rustc-cfg=zephyr300");
+    }
+
+    if kernel_version >= 0x3_02_00 {
+        println!("cargo:rustc-cfg=zephyr320");
+    }
+
+    if kernel_version >= 0x3_03_00 {
+        println!("cargo:rustc-cfg=zephyr330");
+    }
+
     if std::env::var("CONFIG_USERSPACE").expect("CONFIG_USERSPACE must be set") == "y" {
         println!("cargo:rustc-cfg=usermode");
     }
@@ -22,6 +38,11 @@ fn main() {
     if std::env::var("CONFIG_RUST_MUTEX_POOL").expect("CONFIG_RUST_MUTEX_POOL must be set") == "y" {
         println!("cargo:rustc-cfg=mutex_pool");
     }
+    if let Ok(dump) = std::env::var("CONFIG_RUST_MUTEX_POOL_DUMP_ON_EXHAUSTION") {
+        if dump == "y" {
+            println!("cargo:rustc-cfg=mutex_pool_dump");
+        }
+    }
     if std::env::var("CONFIG_POSIX_CLOCK").expect("CONFIG_POSIX_CLOCK must be set") == "y" {
         println!("cargo:rustc-cfg=clock");
     }
@@ -30,4 +51,27 @@ fn main() {
             println!("cargo:rustc-cfg=tls");
         }
     }
+    // max_allocated_bytes is new in 3.1
+    if let Ok(stats) = std::env::var("CONFIG_SYS_HEAP_RUNTIME_STATS") {
+        if stats == "y" && kernel_version >= 0x3_01_00 {
+            println!("cargo:rustc-cfg=heap_stats");
+        }
+    }
+    if let Ok(cpu_mask) = std::env::var("CONFIG_SCHED_CPU_MASK") {
+        if cpu_mask == "y" {
+            println!("cargo:rustc-cfg=sched_cpu_mask");
+        }
+    }
+    // Stack guards and privilege stacks need a stack object laid out by the kernel's macros, which
+    // a heap allocation can't provide
+    let heap_stack_unsupported = [
+        "CONFIG_USERSPACE",
+        "CONFIG_HW_STACK_PROTECTION",
+        "CONFIG_MPU_REQUIRES_POWER_OF_TWO_ALIGNMENT",
+    ]
+    .iter()
+    .any(|var| std::env::var(var).map_or(false, |v| v == "y"));
+    if heap_stack_unsupported {
+        println!("cargo:rustc-cfg=heap_stack_unsupported");
+    }
 }
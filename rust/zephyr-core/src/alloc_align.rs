@@ -0,0 +1,75 @@
+//! Aligned allocation for kernels without k_aligned_alloc and k_heap_aligned_alloc (before 2.5)
+//!
+//! Blocks with a larger alignment than the heap guarantees are over-allocated by the alignment.
+//! The pointer to the start of the block is stored in the word before the returned pointer, so it
+//! can be found again to free the block.
+
+use core::alloc::Layout;
+use core::ptr;
+
+/// Alignment of every block from k_malloc and k_heap_alloc
+pub const MIN_ALIGN: usize = core::mem::align_of::<usize>();
+
+/// Allocate `layout` with `alloc(size)`, which returns `MIN_ALIGN` aligned memory
+pub unsafe fn alloc(layout: Layout, alloc: impl Fn(usize) -> *mut u8) -> *mut u8 {
+    if layout.align() <= MIN_ALIGN {
+        return alloc(layout.size());
+    }
+    // The offset to the next aligned address is at least one word, because both are word aligned,
+    // and at most the alignment.
+    let size = match layout.size().checked_add(layout.align()) {
+        Some(size) => size,
+        None => return ptr::null_mut(),
+    };
+    let block = alloc(size);
+    if block.is_null() {
+        return block;
+    }
+    let aligned = (block as usize | (layout.align() - 1)) + 1;
+    *(aligned as *mut usize).sub(1) = block as usize;
+    aligned as *mut u8
+}
+
+/// Free memory from `alloc` with `free(block)`
+pub unsafe fn dealloc(ptr: *mut u8, layout: Layout, free: impl Fn(*mut u8)) {
+    free(block_start(ptr, layout))
+}
+
+/// Reuse the block if it is big enough, else allocate a new one and move the contents
+pub unsafe fn realloc(
+    ptr: *mut u8,
+    layout: Layout,
+    new_size: usize,
+    alloc: impl Fn(usize) -> *mut u8,
+    free: impl Fn(*mut u8),
+) -> *mut u8 {
+    if new_size <= capacity(ptr, layout) {
+        return ptr;
+    }
+    let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+    let new = self::alloc(new_layout, alloc);
+    if !new.is_null() {
+        ptr::copy_nonoverlapping(ptr, new, layout.size());
+        dealloc(ptr, layout, free);
+    }
+    new
+}
+
+unsafe fn block_start(ptr: *mut u8, layout: Layout) -> *mut u8 {
+    if layout.align() <= MIN_ALIGN {
+        ptr
+    } else {
+        *(ptr as *const usize).sub(1) as *mut u8
+    }
+}
+
+/// Bytes known to be usable at `ptr`. For over-aligned blocks, this includes the unused end of the
+/// over-allocation.
+unsafe fn capacity(ptr: *mut u8, layout: Layout) -> usize {
+    if layout.align() <= MIN_ALIGN {
+        layout.size()
+    } else {
+        let block = block_start(ptr, layout) as usize;
+        block + layout.size() + layout.align() - ptr as usize
+    }
+}
@@ -0,0 +1,193 @@
+use libc::c_int;
+use zephyr_sys::raw::{k_condvar, k_mutex, k_objects, k_timeout_t};
+
+use super::NegErr;
+use crate::kobj::*;
+use crate::mutex::{MutexGuard, MutexSyscalls};
+use crate::time::Timeout;
+use crate::Error;
+
+// Declare the Zephyr struct to be a kernel object
+unsafe impl KObj for k_condvar {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_CONDVAR;
+}
+
+pub use zephyr_sys::raw::k_condvar as KCondvar;
+
+crate::make_static_wrapper!(k_condvar, zephyr_sys::raw::k_condvar);
+
+/// Raw syscall API
+pub trait CondvarSyscalls {
+    unsafe fn k_condvar_init(condvar: &k_condvar) -> c_int;
+    fn k_condvar_signal(condvar: &k_condvar) -> c_int;
+    fn k_condvar_broadcast(condvar: &k_condvar) -> c_int;
+    unsafe fn k_condvar_wait(condvar: &k_condvar, mutex: &k_mutex, timeout: k_timeout_t) -> c_int;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl CondvarSyscalls for $context_struct {
+            unsafe fn k_condvar_init(condvar: &k_condvar) -> c_int {
+                zephyr_sys::syscalls::$context::k_condvar_init(condvar as *const _ as *mut _)
+            }
+
+            fn k_condvar_signal(condvar: &k_condvar) -> c_int {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_condvar_signal(condvar as *const _ as *mut _)
+                }
+            }
+
+            fn k_condvar_broadcast(condvar: &k_condvar) -> c_int {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_condvar_broadcast(
+                        condvar as *const _ as *mut _,
+                    )
+                }
+            }
+
+            unsafe fn k_condvar_wait(
+                condvar: &k_condvar,
+                mutex: &k_mutex,
+                timeout: k_timeout_t,
+            ) -> c_int {
+                zephyr_sys::syscalls::$context::k_condvar_wait(
+                    condvar as *const _ as *mut _,
+                    mutex as *const _ as *mut _,
+                    timeout,
+                )
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+/// Whether a timed wait on a condition variable returned because of the timeout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/// Condition variable like that in std, waiting with a `MutexGuard` held
+///
+/// The kernel releases the mutex while waiting and reacquires it before returning, including on
+/// timeout, so the guard is always handed back locked.
+#[derive(Clone, Copy)]
+pub struct Condvar<'c> {
+    condvar: &'c KCondvar,
+}
+
+// The kernel does all locking
+unsafe impl<'c> Send for Condvar<'c> {}
+unsafe impl<'c> Sync for Condvar<'c> {}
+
+impl<'c> Condvar<'c> {
+    pub const fn new(condvar: &'c KCondvar) -> Self {
+        Condvar { condvar }
+    }
+
+    pub fn kobj(&self) -> &'c KCondvar {
+        self.condvar
+    }
+
+    /// Wait with infinite timeout
+    pub fn wait<'a, T, C: CondvarSyscalls + MutexSyscalls>(
+        &self,
+        guard: MutexGuard<'a, T, C>,
+    ) -> MutexGuard<'a, T, C> {
+        self.checked_wait(guard).expect("condvar wait")
+    }
+
+    /// Like `wait`, returning any error instead of panicking. The kernel holds the mutex again
+    /// whenever it returns, so on error the guard is dropped, unlocking it.
+    pub fn checked_wait<'a, T, C: CondvarSyscalls + MutexSyscalls>(
+        &self,
+        guard: MutexGuard<'a, T, C>,
+    ) -> Result<MutexGuard<'a, T, C>, Error> {
+        unsafe { C::k_condvar_wait(self.condvar, guard.kmutex(), zephyr_sys::raw::K_FOREVER) }
+            .neg_err()?;
+        Ok(guard)
+    }
+
+    /// Wait up to `timeout` for a notification
+    pub fn wait_timeout<'a, T, C: CondvarSyscalls + MutexSyscalls>(
+        &self,
+        guard: MutexGuard<'a, T, C>,
+        timeout: Timeout,
+    ) -> (MutexGuard<'a, T, C>, WaitTimeoutResult) {
+        self.checked_wait_timeout(guard, timeout)
+            .expect("condvar wait")
+    }
+
+    /// Like `wait_timeout`, returning any error other than the timeout instead of panicking. See
+    /// `checked_wait`.
+    pub fn checked_wait_timeout<'a, T, C: CondvarSyscalls + MutexSyscalls>(
+        &self,
+        guard: MutexGuard<'a, T, C>,
+        timeout: Timeout,
+    ) -> Result<(MutexGuard<'a, T, C>, WaitTimeoutResult), Error> {
+        let rc = unsafe { C::k_condvar_wait(self.condvar, guard.kmutex(), timeout.0) };
+        let timed_out = match rc.neg_err() {
+            Ok(_) => false,
+            Err(Error::Again) => true,
+            Err(e) => return Err(e),
+        };
+        Ok((guard, WaitTimeoutResult(timed_out)))
+    }
+
+    /// Wait until `condition` returns false. The condition is checked with the mutex held before
+    /// the first wait and after every wakeup.
+    pub fn wait_while<'a, T, C: CondvarSyscalls + MutexSyscalls, F: FnMut(&mut T) -> bool>(
+        &self,
+        mut guard: MutexGuard<'a, T, C>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T, C> {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Like `wait_while`, returning any error instead of panicking. See `checked_wait`.
+    pub fn checked_wait_while<
+        'a,
+        T,
+        C: CondvarSyscalls + MutexSyscalls,
+        F: FnMut(&mut T) -> bool,
+    >(
+        &self,
+        mut guard: MutexGuard<'a, T, C>,
+        mut condition: F,
+    ) -> Result<MutexGuard<'a, T, C>, Error> {
+        while condition(&mut *guard) {
+            guard = self.checked_wait(guard)?;
+        }
+        Ok(guard)
+    }
+
+    /// Wake one waiting thread
+    pub fn notify_one<C: CondvarSyscalls>(&self) {
+        self.checked_notify_one::<C>().expect("condvar signal")
+    }
+
+    /// Like `notify_one`, returning any error instead of panicking
+    pub fn checked_notify_one<C: CondvarSyscalls>(&self) -> Result<(), Error> {
+        C::k_condvar_signal(self.condvar).neg_err().map(|_| ())
+    }
+
+    /// Wake all waiting threads
+    pub fn notify_all<C: CondvarSyscalls>(&self) {
+        self.checked_notify_all::<C>().expect("condvar broadcast")
+    }
+
+    /// Like `notify_all`, returning any error instead of panicking
+    pub fn checked_notify_all<C: CondvarSyscalls>(&self) -> Result<(), Error> {
+        C::k_condvar_broadcast(self.condvar).neg_err().map(|_| ())
+    }
+}
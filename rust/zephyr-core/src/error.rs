@@ -0,0 +1,98 @@
+use core::fmt;
+
+macro_rules! errors {
+    ($($variant:ident = $errno:ident, $msg:literal;)*) => {
+        /// Error codes returned by the kernel and drivers, from the Zephyr errno set
+        ///
+        /// Codes without a variant of their own are kept in `Other`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Error {
+            $(
+                #[doc = $msg]
+                $variant,
+            )*
+            /// Any other positive errno
+            Other(i32),
+        }
+
+        impl Error {
+            /// From a positive errno, as found in `errno` or negated in return codes
+            pub fn from_errno(errno: i32) -> Self {
+                match errno as u32 {
+                    $(zephyr_sys::raw::$errno => Error::$variant,)*
+                    _ => Error::Other(errno),
+                }
+            }
+
+            /// The positive errno
+            pub fn errno(&self) -> i32 {
+                match *self {
+                    $(Error::$variant => zephyr_sys::raw::$errno as i32,)*
+                    Error::Other(errno) => errno,
+                }
+            }
+
+            fn message(&self) -> Option<&'static str> {
+                match *self {
+                    $(Error::$variant => Some($msg),)*
+                    Error::Other(_) => None,
+                }
+            }
+        }
+    };
+}
+
+errors! {
+    NotPermitted = EPERM, "Not owner";
+    NoEntry = ENOENT, "No such file or directory";
+    NoProcess = ESRCH, "No such context";
+    Interrupted = EINTR, "Interrupted system call";
+    Io = EIO, "I/O error";
+    NoDeviceOrAddress = ENXIO, "No such device or address";
+    TooBig = E2BIG, "Arg list too long";
+    BadFile = EBADF, "Bad file number";
+    Again = EAGAIN, "No more contexts";
+    NoMemory = ENOMEM, "Not enough core";
+    AccessDenied = EACCES, "Permission denied";
+    Fault = EFAULT, "Bad address";
+    Busy = EBUSY, "Mount device busy";
+    Exists = EEXIST, "File exists";
+    NoDevice = ENODEV, "No such device";
+    Invalid = EINVAL, "Invalid argument";
+    NoSpace = ENOSPC, "No space left on device";
+    BrokenPipe = EPIPE, "Broken pipe";
+    Domain = EDOM, "Argument too large";
+    Range = ERANGE, "Result too large";
+    NoMsg = ENOMSG, "Unexpected message type";
+    Deadlock = EDEADLK, "Resource deadlock avoided";
+    NoData = ENODATA, "No message available";
+    Time = ETIME, "STREAMS timeout occurred";
+    Protocol = EPROTO, "Protocol error";
+    BadMsg = EBADMSG, "Invalid STREAMS message";
+    NoSys = ENOSYS, "Function not implemented";
+    NotEmpty = ENOTEMPTY, "Directory not empty";
+    NoBufs = ENOBUFS, "No buffer space available";
+    TimedOut = ETIMEDOUT, "Connection timed out";
+    InProgress = EINPROGRESS, "Operation now in progress";
+    Already = EALREADY, "Operation already in progress";
+    MsgSize = EMSGSIZE, "Message size";
+    NotSupported = ENOTSUP, "Unsupported value";
+    Overflow = EOVERFLOW, "Value overflow";
+    Canceled = ECANCELED, "Operation canceled";
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(msg) => write!(f, "{} (errno {})", msg, self.errno()),
+            None => write!(f, "errno {}", self.errno()),
+        }
+    }
+}
+
+#[cfg(feature = "have_std")]
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        std::io::Error::from_raw_os_error(e.errno())
+    }
+}
@@ -0,0 +1,250 @@
+use zephyr_sys::raw::{k_event, k_objects, k_timeout_t};
+
+use crate::kobj::*;
+use crate::poll_signal::{KPollSignal, KPollSignalSyscalls, Signal};
+use crate::time::Timeout;
+
+// Declare the Zephyr struct to be a kernel object
+unsafe impl KObj for k_event {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_EVENT;
+}
+
+pub use zephyr_sys::raw::k_event as KEvent;
+
+crate::make_static_wrapper!(k_event, zephyr_sys::raw::k_event);
+
+/// Raw syscall API
+pub trait EventSyscalls {
+    unsafe fn k_event_init(event: &k_event);
+    fn k_event_post(event: &k_event, events: u32);
+    fn k_event_set(event: &k_event, events: u32);
+    #[cfg(zephyr320)]
+    fn k_event_set_masked(event: &k_event, events: u32, mask: u32);
+    #[cfg(zephyr320)]
+    fn k_event_clear(event: &k_event, events: u32);
+    fn k_event_wait(event: &k_event, events: u32, reset: bool, timeout: k_timeout_t) -> u32;
+    fn k_event_wait_all(event: &k_event, events: u32, reset: bool, timeout: k_timeout_t) -> u32;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl EventSyscalls for $context_struct {
+            unsafe fn k_event_init(event: &k_event) {
+                zephyr_sys::syscalls::$context::k_event_init(event as *const _ as *mut _)
+            }
+
+            // Newer kernels return the previous events from post, set and clear. Ignore them to
+            // keep one signature across versions.
+            fn k_event_post(event: &k_event, events: u32) {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_event_post(
+                        event as *const _ as *mut _,
+                        events,
+                    );
+                }
+            }
+
+            fn k_event_set(event: &k_event, events: u32) {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_event_set(
+                        event as *const _ as *mut _,
+                        events,
+                    );
+                }
+            }
+
+            #[cfg(zephyr320)]
+            fn k_event_set_masked(event: &k_event, events: u32, mask: u32) {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_event_set_masked(
+                        event as *const _ as *mut _,
+                        events,
+                        mask,
+                    );
+                }
+            }
+
+            #[cfg(zephyr330)]
+            fn k_event_clear(event: &k_event, events: u32) {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_event_clear(
+                        event as *const _ as *mut _,
+                        events,
+                    );
+                }
+            }
+
+            // k_event_clear was added in 3.3. Setting the masked events to zero is the same.
+            #[cfg(all(zephyr320, not(zephyr330)))]
+            fn k_event_clear(event: &k_event, events: u32) {
+                Self::k_event_set_masked(event, 0, events)
+            }
+
+            fn k_event_wait(
+                event: &k_event,
+                events: u32,
+                reset: bool,
+                timeout: k_timeout_t,
+            ) -> u32 {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_event_wait(
+                        event as *const _ as *mut _,
+                        events,
+                        reset,
+                        timeout,
+                    )
+                }
+            }
+
+            fn k_event_wait_all(
+                event: &k_event,
+                events: u32,
+                reset: bool,
+                timeout: k_timeout_t,
+            ) -> u32 {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_event_wait_all(
+                        event as *const _ as *mut _,
+                        events,
+                        reset,
+                        timeout,
+                    )
+                }
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+/// Safe API implemented on the event struct
+pub trait Event {
+    unsafe fn init<C: EventSyscalls>(&self);
+    /// Add `events` to the set of posted events, waking threads whose wait is now satisfied
+    fn post<C: EventSyscalls>(&self, events: u32);
+    /// Replace the set of posted events with `events`
+    fn set<C: EventSyscalls>(&self, events: u32);
+    /// Replace only the events in `mask` with those in `events`, leaving the others posted
+    #[cfg(zephyr320)]
+    fn set_masked<C: EventSyscalls>(&self, events: u32, mask: u32);
+    /// Remove `events` from the set of posted events. Zephyr 3.0 and 3.1 have no way to do this
+    /// atomically, so it is only available from 3.2.
+    #[cfg(zephyr320)]
+    fn clear<C: EventSyscalls>(&self, events: u32);
+    /// Wait up to `timeout` for any (or, with `wait_all`, every) event in `mask`. With `reset`,
+    /// previously posted events are discarded before waiting. Returns the posted events matching
+    /// `mask`, or None on timeout.
+    fn wait<C: EventSyscalls>(
+        &self,
+        mask: u32,
+        wait_all: bool,
+        reset: bool,
+        timeout: Timeout,
+    ) -> Option<u32>;
+    /// Check without waiting whether the events in `mask` are posted. See `wait`.
+    fn check<C: EventSyscalls>(&self, mask: u32, wait_all: bool) -> Option<u32>;
+}
+
+impl Event for k_event {
+    unsafe fn init<C: EventSyscalls>(&self) {
+        C::k_event_init(self)
+    }
+
+    fn post<C: EventSyscalls>(&self, events: u32) {
+        C::k_event_post(self, events)
+    }
+
+    fn set<C: EventSyscalls>(&self, events: u32) {
+        C::k_event_set(self, events)
+    }
+
+    #[cfg(zephyr320)]
+    fn set_masked<C: EventSyscalls>(&self, events: u32, mask: u32) {
+        C::k_event_set_masked(self, events, mask)
+    }
+
+    #[cfg(zephyr320)]
+    fn clear<C: EventSyscalls>(&self, events: u32) {
+        C::k_event_clear(self, events)
+    }
+
+    fn wait<C: EventSyscalls>(
+        &self,
+        mask: u32,
+        wait_all: bool,
+        reset: bool,
+        timeout: Timeout,
+    ) -> Option<u32> {
+        let matched = if wait_all {
+            C::k_event_wait_all(self, mask, reset, timeout.0)
+        } else {
+            C::k_event_wait(self, mask, reset, timeout.0)
+        };
+        if matched != 0 {
+            Some(matched)
+        } else {
+            None
+        }
+    }
+
+    fn check<C: EventSyscalls>(&self, mask: u32, wait_all: bool) -> Option<u32> {
+        self.wait::<C>(mask, wait_all, false, crate::K_NO_WAIT)
+    }
+}
+
+/// An event object paired with a poll signal
+///
+/// k_poll cannot wait on an event object directly. Posting through this also raises the signal so
+/// a poller, such as the futures reactor, wakes up and checks the event again. The signal should
+/// only be waited on by one poller.
+///
+/// Events posted to the underlying `KEvent` some other way, e.g. by C code calling k_event_post,
+/// do not raise the signal. A poller only notices them the next time it is woken for another
+/// reason.
+#[derive(Clone, Copy)]
+pub struct EventSignal<'a> {
+    event: &'a KEvent,
+    signal: &'a KPollSignal,
+}
+
+// The kernel does all locking
+unsafe impl<'a> Send for EventSignal<'a> {}
+unsafe impl<'a> Sync for EventSignal<'a> {}
+
+impl<'a> EventSignal<'a> {
+    pub const fn new(event: &'a KEvent, signal: &'a KPollSignal) -> Self {
+        EventSignal { event, signal }
+    }
+
+    pub fn event(&self) -> &'a KEvent {
+        self.event
+    }
+
+    pub fn signal(&self) -> &'a KPollSignal {
+        self.signal
+    }
+
+    pub fn post<C: EventSyscalls + KPollSignalSyscalls>(&self, events: u32) {
+        self.event.post::<C>(events);
+        self.signal.raise::<C>(events as libc::c_int);
+    }
+
+    pub fn set<C: EventSyscalls + KPollSignalSyscalls>(&self, events: u32) {
+        self.event.set::<C>(events);
+        self.signal.raise::<C>(events as libc::c_int);
+    }
+
+    #[cfg(zephyr320)]
+    pub fn set_masked<C: EventSyscalls + KPollSignalSyscalls>(&self, events: u32, mask: u32) {
+        self.event.set_masked::<C>(events, mask);
+        self.signal.raise::<C>(events as libc::c_int);
+    }
+
+    /// Clearing cannot satisfy a wait, so the signal is not raised
+    #[cfg(zephyr320)]
+    pub fn clear<C: EventSyscalls>(&self, events: u32) {
+        self.event.clear::<C>(events)
+    }
+}
@@ -36,8 +36,10 @@ impl<T> StaticKObj<T> {
 }
 
 impl<T: KObj> StaticKObj<T> {
-    pub fn as_ptr(&self) -> *const T {
-        unsafe { (*self.0.get()).as_ptr() }
+    // MaybeUninit is repr(transparent), so the cast is the same as MaybeUninit::as_ptr but is
+    // usable in a const fn.
+    pub const fn as_ptr(&self) -> *const T {
+        self.0.get() as *const T
     }
 
     pub fn as_mut_ptr(&self) -> *mut T {
@@ -84,6 +86,11 @@ macro_rules! make_static_wrapper {
                 pub fn kobj(&self) -> &$k_path {
                     self.deref().deref()
                 }
+
+                /// Pointer to the real k_obj type. Unlike deref, usable in a static initializer.
+                pub const fn as_ptr(&self) -> *const $k_path {
+                    self.0.as_ptr()
+                }
             }
 
             impl Deref for $k_type {
@@ -1,20 +1,36 @@
 #![cfg_attr(not(feature = "have_std"), no_std)]
+#![feature(allocator_api)]
 #![feature(never_type)]
 
 #[macro_use]
 extern crate derive_more;
 
+#[cfg(not(zephyr250))]
+mod alloc_align;
+#[cfg(zephyr250)]
+pub mod condvar;
+mod error;
+#[cfg(zephyr300)]
+pub mod event;
 pub mod kobj;
 pub mod memdomain;
 pub mod mempool;
+pub mod msgq;
 pub mod mutex;
 pub mod mutex_alloc;
+pub mod pipe;
 pub mod poll;
 mod poll_signal;
+pub mod queue;
 pub mod semaphore;
+pub mod slab;
 pub mod thread;
+pub mod timer;
+#[cfg(zephyr260)]
+pub mod work;
 mod time;
 
+pub use error::Error;
 pub use time::*;
 use zephyr_sys::raw::KERNEL_VERSION_NUMBER;
 use core::fmt;
@@ -39,19 +55,38 @@ impl fmt::Display for KernelVersion {
 
 // Use this mem pool for global allocs instead of kmalloc
 #[cfg(mempool)]
-crate::global_sys_mem_pool!(rust_std_mem_pool);
+crate::global_sys_mem_pool!(
+    rust_std_mem_pool,
+    zephyr_sys::raw::CONFIG_RUST_ALLOC_POOL_TIMEOUT_MS as i32
+);
+
+/// Whether the current thread is in user mode. For kernel-only APIs reachable without a context
+/// type parameter.
+#[cfg(usermode)]
+pub(crate) fn is_user_context() -> bool {
+    extern "C" {
+        /// k_is_user_context, which is inline. From rust-smem.c.
+        fn rust_is_user_context() -> bool;
+    }
+    unsafe { rust_is_user_context() }
+}
+
+#[cfg(not(usermode))]
+pub(crate) fn is_user_context() -> bool {
+    false
+}
 
 /// Convert a negative error code to a Result
 pub trait NegErr {
-    fn neg_err(&self) -> Result<u32, u32>;
+    fn neg_err(&self) -> Result<u32, Error>;
 }
 
 impl NegErr for i32 {
-    fn neg_err(&self) -> Result<u32, u32> {
+    fn neg_err(&self) -> Result<u32, Error> {
         if *self >= 0 {
             Ok(*self as u32)
         } else {
-            Err((-*self) as u32)
+            Err(Error::from_errno(self.wrapping_neg()))
         }
     }
 }
@@ -180,8 +215,9 @@ pub mod kernel {
         unreachable!()
     }
 
+    /// Also reports failures to the alloc error hook
     fn check_align(ptr: *mut u8, layout: Layout) -> *mut u8 {
-        if ptr as usize & (layout.align() - 1) != 0 {
+        let ret = if ptr as usize & (layout.align() - 1) != 0 {
             unsafe {
                 zephyr_sys::raw::printk(
                     "Rust unsatisfied alloc alignment\n\0".as_ptr() as *const libc::c_char
@@ -190,7 +226,11 @@ pub mod kernel {
             core::ptr::null_mut()
         } else {
             ptr
+        };
+        if ret.is_null() {
+            crate::mempool::alloc_failed(layout);
         }
+        ret
     }
 
     pub struct KMalloc;
@@ -199,7 +239,8 @@ pub mod kernel {
         #[inline]
         unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
             #[cfg(not(zephyr250))]
-            let ret = zephyr_sys::raw::k_malloc(layout.size()) as *mut _;
+            let ret =
+                crate::alloc_align::alloc(layout, |size| zephyr_sys::raw::k_malloc(size) as *mut _);
             #[cfg(zephyr250)]
             let ret = zephyr_sys::raw::k_aligned_alloc(layout.align(), layout.size()) as *mut _;
 
@@ -208,14 +249,42 @@ pub mod kernel {
 
         #[inline]
         unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            // k_calloc only guarantees word alignment
+            if layout.align() > core::mem::align_of::<usize>() {
+                let ret = self.alloc(layout);
+                if !ret.is_null() {
+                    ptr::write_bytes(ret, 0, layout.size());
+                }
+                return ret;
+            }
             let ret = zephyr_sys::raw::k_calloc(1, layout.size()) as *mut _;
             check_align(ret, layout)
         }
 
         #[inline]
         unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+            #[cfg(not(zephyr250))]
+            crate::alloc_align::dealloc(ptr, _layout, |block| {
+                zephyr_sys::raw::k_free(block as *mut _)
+            });
+            #[cfg(zephyr250)]
             zephyr_sys::raw::k_free(ptr as *mut _)
         }
+
+        #[cfg(not(zephyr250))]
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let ret = crate::alloc_align::realloc(
+                ptr,
+                layout,
+                new_size,
+                |size| zephyr_sys::raw::k_malloc(size) as *mut _,
+                |block| zephyr_sys::raw::k_free(block as *mut _),
+            );
+            check_align(
+                ret,
+                Layout::from_size_align_unchecked(new_size, layout.align()),
+            )
+        }
     }
 }
 
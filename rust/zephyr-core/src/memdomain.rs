@@ -1,9 +1,18 @@
+#[cfg(usermode)]
+use core::cell::UnsafeCell;
 #[cfg(not(usermode))]
 use core::marker::PhantomData;
+#[cfg(usermode)]
+use core::mem::MaybeUninit;
+#[cfg(usermode)]
+use core::sync::atomic::{AtomicBool, Ordering};
 
 pub use zephyr_sys::raw::k_mem_domain;
+#[cfg(usermode)]
+pub use zephyr_sys::raw::k_mem_partition;
 
 use crate::thread::ThreadId;
+use crate::{Error, NegErr};
 
 #[cfg(usermode)]
 pub struct MemDomain<'a>(&'a k_mem_domain);
@@ -20,20 +29,284 @@ impl<'a> MemDomain<'a> {
         MemDomain(PhantomData)
     }
 
-    pub fn add_thread<C: MemDomainAPI>(&self, _thread: ThreadId) {
+    pub fn add_thread<C: MemDomainAPI>(&self, _thread: ThreadId) -> Result<(), Error> {
+        #[cfg(usermode)]
+        return C::k_mem_domain_add_thread(self.0, _thread);
+        #[cfg(not(usermode))]
+        Ok(())
+    }
+
+    /// Move a thread back to the default domain, or to no domain before Zephyr 2.5
+    pub fn remove_thread<C: MemDomainAPI>(_thread: ThreadId) -> Result<(), Error> {
         #[cfg(usermode)]
-        C::k_mem_domain_add_thread(self.0, _thread)
+        return C::k_mem_domain_remove_thread(_thread);
+        #[cfg(not(usermode))]
+        Ok(())
+    }
+
+    /// Grant threads in the domain access to the partition
+    pub fn add_partition<C: MemDomainAPI>(&self, _part: &'a MemPartition) -> Result<(), Error> {
+        #[cfg(usermode)]
+        return C::k_mem_domain_add_partition(self.0, _part.kobj());
+        #[cfg(not(usermode))]
+        Ok(())
+    }
+
+    pub fn remove_partition<C: MemDomainAPI>(&self, _part: &MemPartition) -> Result<(), Error> {
+        #[cfg(usermode)]
+        return C::k_mem_domain_remove_partition(self.0, _part.kobj());
+        #[cfg(not(usermode))]
+        Ok(())
+    }
+}
+
+/// Memory domain functions returned void before Zephyr 2.6
+trait DomainResult {
+    fn into_result(self) -> Result<(), Error>;
+}
+
+impl DomainResult for () {
+    fn into_result(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl DomainResult for libc::c_int {
+    fn into_result(self) -> Result<(), Error> {
+        self.neg_err().map(|_| ())
     }
 }
 
 pub trait MemDomainAPI {
-    fn k_mem_domain_add_thread(domain: &k_mem_domain, thread: ThreadId);
+    fn k_mem_domain_add_thread(domain: &k_mem_domain, thread: ThreadId) -> Result<(), Error>;
+    #[cfg(usermode)]
+    fn k_mem_domain_remove_thread(thread: ThreadId) -> Result<(), Error>;
+    /// Initialize with no partitions
+    #[cfg(usermode)]
+    unsafe fn k_mem_domain_init(domain: *mut k_mem_domain) -> Result<(), Error>;
+    #[cfg(usermode)]
+    fn k_mem_domain_add_partition(
+        domain: &k_mem_domain,
+        part: &k_mem_partition,
+    ) -> Result<(), Error>;
+    #[cfg(usermode)]
+    fn k_mem_domain_remove_partition(
+        domain: &k_mem_domain,
+        part: &k_mem_partition,
+    ) -> Result<(), Error>;
 }
 
 impl MemDomainAPI for crate::context::Kernel {
-    fn k_mem_domain_add_thread(domain: &k_mem_domain, thread: ThreadId) {
+    fn k_mem_domain_add_thread(domain: &k_mem_domain, thread: ThreadId) -> Result<(), Error> {
         unsafe {
             zephyr_sys::raw::k_mem_domain_add_thread(domain as *const _ as *mut _, thread.tid())
+                .into_result()
+        }
+    }
+
+    #[cfg(usermode)]
+    fn k_mem_domain_remove_thread(thread: ThreadId) -> Result<(), Error> {
+        unsafe {
+            // Threads always belong to a domain since 2.5
+            #[cfg(zephyr250)]
+            return zephyr_sys::raw::k_mem_domain_add_thread(
+                core::ptr::addr_of_mut!(zephyr_sys::raw::k_mem_domain_default),
+                thread.tid(),
+            )
+            .into_result();
+            #[cfg(not(zephyr250))]
+            zephyr_sys::raw::k_mem_domain_remove_thread(thread.tid()).into_result()
+        }
+    }
+
+    #[cfg(usermode)]
+    unsafe fn k_mem_domain_init(domain: *mut k_mem_domain) -> Result<(), Error> {
+        zephyr_sys::raw::k_mem_domain_init(domain, 0, core::ptr::null_mut()).into_result()
+    }
+
+    #[cfg(usermode)]
+    fn k_mem_domain_add_partition(
+        domain: &k_mem_domain,
+        part: &k_mem_partition,
+    ) -> Result<(), Error> {
+        unsafe {
+            zephyr_sys::raw::k_mem_domain_add_partition(
+                domain as *const _ as *mut _,
+                part as *const _ as *mut _,
+            )
+            .into_result()
+        }
+    }
+
+    #[cfg(usermode)]
+    fn k_mem_domain_remove_partition(
+        domain: &k_mem_domain,
+        part: &k_mem_partition,
+    ) -> Result<(), Error> {
+        unsafe {
+            zephyr_sys::raw::k_mem_domain_remove_partition(
+                domain as *const _ as *mut _,
+                part as *const _ as *mut _,
+            )
+            .into_result()
+        }
+    }
+}
+
+/// Storage for a domain set up at runtime. Declare with `k_mem_domain_define!`, which keeps it out
+/// of reach of user threads.
+#[cfg(usermode)]
+pub struct StaticMemDomain {
+    domain: UnsafeCell<MaybeUninit<k_mem_domain>>,
+    /// Set while `init` runs, and for good once it succeeds
+    claimed: AtomicBool,
+    initialized: AtomicBool,
+}
+#[cfg(not(usermode))]
+pub struct StaticMemDomain(());
+
+unsafe impl Sync for StaticMemDomain {}
+
+impl StaticMemDomain {
+    /// Must be zeroed with `zero` before use
+    #[cfg(usermode)]
+    pub const unsafe fn uninit() -> Self {
+        StaticMemDomain {
+            domain: UnsafeCell::new(MaybeUninit::uninit()),
+            claimed: AtomicBool::new(false),
+            initialized: AtomicBool::new(false),
+        }
+    }
+    #[cfg(not(usermode))]
+    pub const unsafe fn uninit() -> Self {
+        StaticMemDomain(())
+    }
+
+    /// For storage in a noinit section. Only before any other use.
+    pub unsafe fn zero(&self) {
+        #[cfg(usermode)]
+        core::ptr::write_bytes(self as *const Self as *mut Self, 0, 1);
+    }
+
+    /// Initialize the domain with `parts`. Fails with `Error::Already` if already initialized, or
+    /// being initialized by another thread. If adding a partition fails, the domain is left
+    /// uninitialized and `init` may be tried again.
+    pub fn init<C: MemDomainAPI>(
+        &'static self,
+        parts: &[&'static MemPartition],
+    ) -> Result<MemDomain<'static>, Error> {
+        #[cfg(usermode)]
+        {
+            if self.claimed.swap(true, Ordering::AcqRel) {
+                return Err(Error::Already);
+            }
+            let result = unsafe { self.init_domain::<C>(parts) };
+            match result {
+                Ok(_) => self.initialized.store(true, Ordering::Release),
+                Err(_) => self.claimed.store(false, Ordering::Release),
+            }
+            result
+        }
+        #[cfg(not(usermode))]
+        {
+            let _ = parts;
+            Ok(MemDomain::new())
+        }
+    }
+
+    /// Unsafe because only the thread that claimed the domain may call this
+    #[cfg(usermode)]
+    unsafe fn init_domain<C: MemDomainAPI>(
+        &'static self,
+        parts: &[&'static MemPartition],
+    ) -> Result<MemDomain<'static>, Error> {
+        C::k_mem_domain_init((*self.domain.get()).as_mut_ptr())?;
+        let domain = MemDomain::new(&*(*self.domain.get()).as_ptr());
+        for part in parts {
+            domain.add_partition::<C>(part)?;
+        }
+        Ok(domain)
+    }
+
+    /// The domain, if initialized
+    pub fn get(&'static self) -> Option<MemDomain<'static>> {
+        #[cfg(usermode)]
+        return if self.initialized.load(Ordering::Acquire) {
+            Some(unsafe { MemDomain::new(&*(*self.domain.get()).as_ptr()) })
+        } else {
+            None
+        };
+        #[cfg(not(usermode))]
+        Some(MemDomain::new())
+    }
+}
+
+/// An app memory partition. Declare with `k_appmem_partition_define!`, or use one defined in C
+/// with `static_mem_partition!`.
+#[cfg(usermode)]
+#[repr(transparent)]
+pub struct MemPartition(UnsafeCell<MaybeUninit<k_mem_partition>>);
+#[cfg(not(usermode))]
+pub struct MemPartition(());
+
+unsafe impl Sync for MemPartition {}
+
+#[cfg(usermode)]
+extern "C" {
+    /// K_MEM_PARTITION_P_RW_U_RW, from rust-smem.c
+    static rust_appmem_partition_attr: zephyr_sys::raw::k_mem_partition_attr_t;
+}
+
+impl MemPartition {
+    /// Must be initialized with `init` before use
+    #[cfg(usermode)]
+    pub const unsafe fn uninit() -> Self {
+        MemPartition(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+    #[cfg(not(usermode))]
+    pub const unsafe fn uninit() -> Self {
+        MemPartition(())
+    }
+
+    /// Use a partition defined in C
+    #[cfg(usermode)]
+    pub unsafe fn from_raw(part: &k_mem_partition) -> &MemPartition {
+        &*(part as *const k_mem_partition as *const MemPartition)
+    }
+
+    /// Set the bounds of the partition, readable and writable by user threads. Only before adding
+    /// it to a domain.
+    pub unsafe fn init(&self, _start: *const u8, _size: usize) {
+        #[cfg(usermode)]
+        (*self.0.get()).write(k_mem_partition {
+            start: _start as usize as _,
+            size: _size as _,
+            attr: rust_appmem_partition_attr,
+        });
+    }
+
+    #[cfg(usermode)]
+    pub fn kobj(&self) -> &k_mem_partition {
+        unsafe { &*(*self.0.get()).as_ptr() }
+    }
+}
+
+/// Entry in the table the kernel uses to zero the bss of each app memory partition at boot, as
+/// created by K_APPMEM_PARTITION_DEFINE
+#[repr(C)]
+pub struct AppRegion {
+    bss_start: *const u8,
+    /// The address of a linker symbol is the size
+    bss_size: *const u8,
+}
+
+unsafe impl Sync for AppRegion {}
+
+impl AppRegion {
+    pub const fn new(bss_start: *const u8, bss_size: *const u8) -> Self {
+        AppRegion {
+            bss_start,
+            bss_size,
         }
     }
 }
@@ -59,3 +332,27 @@ macro_rules! static_mem_domain {
         $crate::memdomain::MemDomain::new()
     }};
 }
+
+/// Get a static reference to a partition defined with K_APPMEM_PARTITION_DEFINE
+#[cfg(usermode)]
+#[macro_export]
+macro_rules! static_mem_partition {
+    ($part:ident) => {{
+        extern "C" {
+            #[no_mangle]
+            static $part: $crate::memdomain::k_mem_partition;
+        }
+
+        unsafe { $crate::memdomain::MemPartition::from_raw(&$part) }
+    }};
+}
+/// Get a static reference to a partition defined with K_APPMEM_PARTITION_DEFINE
+#[cfg(not(usermode))]
+#[macro_export]
+macro_rules! static_mem_partition {
+    ($part:ident) => {{
+        static PART: $crate::memdomain::MemPartition =
+            unsafe { $crate::memdomain::MemPartition::uninit() };
+        &PART
+    }};
+}
@@ -1,47 +1,261 @@
-use core::alloc::{GlobalAlloc, Layout};
+use core::alloc::{AllocError, Allocator, GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+#[cfg(heap_stats)]
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::time::Duration;
 
 pub use zephyr_sys::raw::k_heap;
-use zephyr_sys::raw::K_NO_WAIT;
+use zephyr_sys::raw::{k_timeout_t, K_NO_WAIT};
 
-pub struct MempoolAlloc(pub &'static k_heap);
+use crate::Timeout;
+
+unsafe fn heap_alloc(kheap: *mut k_heap, layout: Layout, timeout: k_timeout_t) -> *mut u8 {
+    #[cfg(zephyr250)]
+    {
+        zephyr_sys::raw::k_heap_aligned_alloc(kheap, layout.align(), layout.size(), timeout)
+            as *mut _
+    }
+    #[cfg(not(zephyr250))]
+    {
+        crate::alloc_align::alloc(layout, |size| {
+            zephyr_sys::raw::k_heap_alloc(kheap, size, timeout) as *mut _
+        })
+    }
+}
+
+unsafe fn heap_free(kheap: *mut k_heap, ptr: *mut u8, _layout: Layout) {
+    #[cfg(zephyr250)]
+    zephyr_sys::raw::k_heap_free(kheap, ptr as *mut _);
+    #[cfg(not(zephyr250))]
+    crate::alloc_align::dealloc(ptr, _layout, |block| {
+        zephyr_sys::raw::k_heap_free(kheap, block as *mut _)
+    });
+}
+
+/// Usage of a heap, from sys_heap_runtime_stats_get
+#[cfg(heap_stats)]
+#[derive(Clone, Copy, Debug)]
+pub struct HeapStats {
+    pub free_bytes: usize,
+    pub allocated_bytes: usize,
+    /// Most bytes ever allocated at once
+    pub max_allocated_bytes: usize,
+}
+
+#[cfg(heap_stats)]
+impl fmt::Display for HeapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes allocated, {} free, max {} allocated",
+            self.allocated_bytes, self.free_bytes, self.max_allocated_bytes
+        )
+    }
+}
+
+#[cfg(heap_stats)]
+fn heap_stats(kheap: *mut k_heap) -> HeapStats {
+    unsafe {
+        let mut stats: zephyr_sys::raw::sys_memory_stats = core::mem::zeroed();
+        zephyr_sys::raw::sys_heap_runtime_stats_get(&mut (*kheap).heap, &mut stats);
+        HeapStats {
+            free_bytes: stats.free_bytes as usize,
+            allocated_bytes: stats.allocated_bytes as usize,
+            max_allocated_bytes: stats.max_allocated_bytes as usize,
+        }
+    }
+}
+
+/// Stats for the heap behind the default Rust global allocator, either the Rust heap memory pool
+/// or the k_malloc heap
+#[cfg(heap_stats)]
+pub fn global_heap_stats() -> HeapStats {
+    extern "C" {
+        #[cfg(mempool)]
+        #[link_name = "rust_std_mem_pool"]
+        static mut GLOBAL_HEAP: k_heap;
+        #[cfg(not(mempool))]
+        #[link_name = "_system_heap"]
+        static mut GLOBAL_HEAP: k_heap;
+    }
+
+    heap_stats(unsafe { core::ptr::addr_of_mut!(GLOBAL_HEAP) })
+}
+
+static ALLOC_ERROR_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Call `hook` with the layout of each failed allocation by `MempoolAlloc` or `KMalloc`
+///
+/// This runs before the failure is returned to the caller, which for most of std means before the
+/// allocation error handler aborts. Replaces `default_alloc_error_hook`.
+pub fn set_alloc_error_hook(hook: fn(Layout)) {
+    ALLOC_ERROR_HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+/// Prints the size and alignment of the failed allocation
+pub fn default_alloc_error_hook(layout: Layout) {
+    unsafe {
+        zephyr_sys::raw::printk(
+            "Rust alloc of %lu bytes (align %lu) failed\n\0".as_ptr() as *const libc::c_char,
+            layout.size() as libc::c_ulong,
+            layout.align() as libc::c_ulong,
+        );
+    }
+}
+
+pub(crate) fn alloc_failed(layout: Layout) {
+    let hook = ALLOC_ERROR_HOOK.load(Ordering::Acquire);
+    if hook.is_null() {
+        default_alloc_error_hook(layout)
+    } else {
+        let hook: fn(Layout) = unsafe { core::mem::transmute(hook) };
+        hook(layout)
+    }
+}
+
+pub struct MempoolAlloc {
+    heap: &'static k_heap,
+    timeout_ms: i32,
+}
 
 unsafe impl Send for MempoolAlloc {}
 unsafe impl Sync for MempoolAlloc {}
 
+impl MempoolAlloc {
+    /// Allocations fail immediately if the heap is full
+    pub const fn new(heap: &'static k_heap) -> Self {
+        Self::with_timeout_ms(heap, 0)
+    }
+
+    /// Allocations wait up to `timeout_ms` for memory to be freed, or forever if negative
+    ///
+    /// Allocations from interrupts or user mode never wait
+    pub const fn with_timeout_ms(heap: &'static k_heap, timeout_ms: i32) -> Self {
+        MempoolAlloc { heap, timeout_ms }
+    }
+
+    fn timeout(&self) -> k_timeout_t {
+        if self.timeout_ms == 0
+            || unsafe { zephyr_sys::raw::k_is_in_isr() }
+            || crate::is_user_context()
+        {
+            K_NO_WAIT
+        } else if self.timeout_ms < 0 {
+            crate::K_FOREVER.0
+        } else {
+            Timeout::from(&Duration::from_millis(self.timeout_ms as u64)).0
+        }
+    }
+
+    #[cfg(heap_stats)]
+    pub fn stats(&self) -> HeapStats {
+        heap_stats(self.heap as *const _ as *mut _)
+    }
+}
+
 unsafe impl GlobalAlloc for MempoolAlloc {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let kheap = self.0 as *const _ as *mut _;
-        #[cfg(zephyr250)]
-        {
-            zephyr_sys::raw::k_heap_aligned_alloc(kheap, layout.align(), layout.size(), K_NO_WAIT) as *mut _
+        let ret = heap_alloc(self.heap as *const _ as *mut _, layout, self.timeout());
+        if ret.is_null() {
+            alloc_failed(layout);
         }
-        #[cfg(not(zephyr250))]
-        {
-            let ret = zephyr_sys::raw::k_heap_alloc(kheap, layout.size(), K_NO_WAIT) as *mut _;
-            if ret as usize & (layout.align() - 1) != 0 {
-                zephyr_sys::raw::printk(
-                    "Rust unsatisfied alloc alignment\n\0".as_ptr() as *const libc::c_char
-                );
-                core::ptr::null_mut()
-            } else {
-                ret
-            }
+        ret
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        heap_free(self.heap as *const _ as *mut _, ptr, layout)
+    }
+
+    #[cfg(not(zephyr250))]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let kheap = self.heap as *const _ as *mut _;
+        let timeout = self.timeout();
+        let ret = crate::alloc_align::realloc(
+            ptr,
+            layout,
+            new_size,
+            |size| zephyr_sys::raw::k_heap_alloc(kheap, size, timeout) as *mut _,
+            |block| zephyr_sys::raw::k_heap_free(kheap, block as *mut _),
+        );
+        if ret.is_null() {
+            alloc_failed(Layout::from_size_align_unchecked(new_size, layout.align()));
         }
+        ret
     }
+}
+
+/// A k_heap usable as an `Allocator`, e.g. `Box::new_in(x, &HEAP)` or `Vec::new_in(&HEAP)`
+///
+/// Declare one with `k_heap_define!`, which can place it in an app memory partition private to
+/// some user threads. The heap is not a kernel object, so any thread that can access the memory
+/// can allocate from it. Allocations never wait, and failures are only reported to the caller, not
+/// to the alloc error hook.
+#[repr(transparent)]
+pub struct KHeap(UnsafeCell<MaybeUninit<k_heap>>);
 
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
-        let kheap = self.0 as *const _ as *mut _;
-        zephyr_sys::raw::k_heap_free(kheap, ptr as *mut _)
+unsafe impl Send for KHeap {}
+unsafe impl Sync for KHeap {}
+
+impl KHeap {
+    /// Must be initialized with `init` before use
+    pub const unsafe fn uninit() -> Self {
+        KHeap(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+
+    /// Use a heap defined in C, e.g. with K_HEAP_DEFINE
+    pub unsafe fn from_raw(kheap: &k_heap) -> &KHeap {
+        &*(kheap as *const k_heap as *const KHeap)
+    }
+
+    /// Set up the heap to allocate from `size` bytes at `mem`. Only from kernel mode.
+    pub unsafe fn init(&self, mem: *mut u8, size: usize) {
+        zephyr_sys::raw::k_heap_init(self.as_ptr(), mem as *mut _, size)
+    }
+
+    pub const fn as_ptr(&self) -> *mut k_heap {
+        self.0.get() as *mut k_heap
+    }
+
+    #[cfg(heap_stats)]
+    pub fn stats(&self) -> HeapStats {
+        heap_stats(self.as_ptr())
+    }
+}
+
+unsafe impl Allocator for KHeap {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = if layout.size() == 0 {
+            // Zero sized allocations only need an aligned, non-null pointer
+            layout.align() as *mut u8
+        } else {
+            unsafe { heap_alloc(self.as_ptr(), layout, K_NO_WAIT) }
+        };
+        NonNull::new(ptr)
+            .map(|ptr| NonNull::slice_from_raw_parts(ptr, layout.size()))
+            .ok_or(AllocError)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            heap_free(self.as_ptr(), ptr.as_ptr(), layout)
+        }
     }
 }
 
 /// Assign a Zephyr k_heap as #[global_allocator]
 ///
 /// This should be defined with K_HEAP_DEFINE and granted permission to any
-/// Rust threads that need to use libstd or alloc.
+/// Rust threads that need to use libstd or alloc. An optional timeout in ms
+/// makes allocations wait for memory; see `MempoolAlloc::with_timeout_ms`.
 #[macro_export]
 macro_rules! global_sys_mem_pool {
     ($pool:ident) => {
+        $crate::global_sys_mem_pool!($pool, 0);
+    };
+    ($pool:ident, $timeout_ms:expr) => {
         extern "C" {
             #[no_mangle]
             static $pool: $crate::mempool::k_heap;
@@ -49,6 +263,6 @@ macro_rules! global_sys_mem_pool {
 
         #[global_allocator]
         static GLOBAL: $crate::mempool::MempoolAlloc =
-            $crate::mempool::MempoolAlloc(unsafe { &$pool });
+            $crate::mempool::MempoolAlloc::with_timeout_ms(unsafe { &$pool }, $timeout_ms);
     };
 }
@@ -0,0 +1,202 @@
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+use libc::{c_int, c_void};
+use zephyr_sys::raw::{k_msgq, k_objects, k_timeout_t};
+
+use super::NegErr;
+use crate::kobj::*;
+use crate::time::Timeout;
+use crate::Error;
+
+// Declare the Zephyr struct to be a kernel object
+unsafe impl KObj for k_msgq {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_MSGQ;
+}
+
+pub use zephyr_sys::raw::k_msgq as KMsgq;
+
+crate::make_static_wrapper!(k_msgq, zephyr_sys::raw::k_msgq);
+
+/// Raw syscall API
+pub trait MsgqSyscalls {
+    unsafe fn k_msgq_put(msgq: &k_msgq, data: *const c_void, timeout: k_timeout_t) -> c_int;
+    unsafe fn k_msgq_get(msgq: &k_msgq, data: *mut c_void, timeout: k_timeout_t) -> c_int;
+    unsafe fn k_msgq_peek(msgq: &k_msgq, data: *mut c_void) -> c_int;
+    fn k_msgq_purge(msgq: &k_msgq);
+    fn k_msgq_num_free_get(msgq: &k_msgq) -> u32;
+    fn k_msgq_num_used_get(msgq: &k_msgq) -> u32;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl MsgqSyscalls for $context_struct {
+            unsafe fn k_msgq_put(
+                msgq: &k_msgq,
+                data: *const c_void,
+                timeout: k_timeout_t,
+            ) -> c_int {
+                zephyr_sys::syscalls::$context::k_msgq_put(
+                    msgq as *const _ as *mut _,
+                    data as *mut _,
+                    timeout,
+                )
+            }
+
+            unsafe fn k_msgq_get(msgq: &k_msgq, data: *mut c_void, timeout: k_timeout_t) -> c_int {
+                zephyr_sys::syscalls::$context::k_msgq_get(
+                    msgq as *const _ as *mut _,
+                    data,
+                    timeout,
+                )
+            }
+
+            unsafe fn k_msgq_peek(msgq: &k_msgq, data: *mut c_void) -> c_int {
+                zephyr_sys::syscalls::$context::k_msgq_peek(msgq as *const _ as *mut _, data)
+            }
+
+            fn k_msgq_purge(msgq: &k_msgq) {
+                unsafe { zephyr_sys::syscalls::$context::k_msgq_purge(msgq as *const _ as *mut _) }
+            }
+
+            fn k_msgq_num_free_get(msgq: &k_msgq) -> u32 {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_msgq_num_free_get(msgq as *const _ as *mut _)
+                }
+            }
+
+            fn k_msgq_num_used_get(msgq: &k_msgq) -> u32 {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_msgq_num_used_get(msgq as *const _ as *mut _)
+                }
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+/// API implemented on the msgq struct that is not a syscall
+pub trait Msgq {
+    /// Initialize the queue to use `buffer` as its ring buffer of `max_msgs` messages of
+    /// `msg_size` bytes each.
+    ///
+    /// This is not a system call. Only usable from kernel mode.
+    unsafe fn init(&self, buffer: *mut u8, msg_size: usize, max_msgs: u32);
+}
+
+impl Msgq for k_msgq {
+    unsafe fn init(&self, buffer: *mut u8, msg_size: usize, max_msgs: u32) {
+        zephyr_sys::raw::k_msgq_init(
+            self as *const _ as *mut _,
+            buffer as *mut _,
+            msg_size as _,
+            max_msgs,
+        )
+    }
+}
+
+/// Message queue carrying fixed-size messages of type `T`
+///
+/// Messages are copied in and out of the kernel's ring buffer, so `T` must be `Copy`. Creating
+/// this is unsafe because the kernel object must have been initialized with a message size of
+/// `size_of::<T>()` and must not be used to carry any other type.
+pub struct MsgQueue<'a, T: Copy> {
+    msgq: *const KMsgq,
+    _marker: PhantomData<(&'a KMsgq, T)>,
+}
+
+// The kernel does all locking. Messages may be sent to another thread.
+unsafe impl<'a, T: Copy + Send> Send for MsgQueue<'a, T> {}
+unsafe impl<'a, T: Copy + Send> Sync for MsgQueue<'a, T> {}
+
+impl<'a, T: Copy> Clone for MsgQueue<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: Copy> Copy for MsgQueue<'a, T> {}
+
+impl<'a, T: Copy> MsgQueue<'a, T> {
+    pub const unsafe fn new(msgq: &'a KMsgq) -> Self {
+        Self::from_ptr(msgq)
+    }
+
+    /// Like `new`, but usable in a static initializer where the kobject is only available as a
+    /// pointer to uninitialized storage.
+    pub const unsafe fn from_ptr(msgq: *const KMsgq) -> Self {
+        MsgQueue {
+            msgq,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the underlying kobject, e.g. to grant a thread access to it.
+    pub fn kobj(&self) -> &'a KMsgq {
+        unsafe { &*self.msgq }
+    }
+
+    /// Put with infinite timeout. Fails only if the queue is purged while waiting.
+    pub fn put<C: MsgqSyscalls>(&self, msg: &T) -> Result<(), Error> {
+        self.put_timeout::<C>(msg, crate::K_FOREVER)
+    }
+
+    /// Put, waiting up to `timeout` for space in the queue. Fails with `Error::Again` on timeout.
+    pub fn put_timeout<C: MsgqSyscalls>(&self, msg: &T, timeout: Timeout) -> Result<(), Error> {
+        unsafe { C::k_msgq_put(self.kobj(), msg as *const T as *const c_void, timeout.0) }
+            .neg_err()
+            .map(|_| ())
+    }
+
+    /// Put without waiting. Returns `Error::NoMsg` if the queue is full.
+    pub fn try_put<C: MsgqSyscalls>(&self, msg: &T) -> Result<(), Error> {
+        self.put_timeout::<C>(msg, crate::K_NO_WAIT)
+    }
+
+    /// Get with infinite timeout
+    pub fn get<C: MsgqSyscalls>(&self) -> Result<T, Error> {
+        self.get_timeout::<C>(crate::K_FOREVER)
+    }
+
+    /// Get, waiting up to `timeout` for a message. Fails with `Error::Again` on timeout, or
+    /// `Error::NoMsg` if the queue is empty and no waiting was requested.
+    pub fn get_timeout<C: MsgqSyscalls>(&self, timeout: Timeout) -> Result<T, Error> {
+        let mut msg = MaybeUninit::<T>::uninit();
+        unsafe { C::k_msgq_get(self.kobj(), msg.as_mut_ptr() as *mut c_void, timeout.0) }
+            .neg_err()
+            .map(|_| unsafe { msg.assume_init() })
+    }
+
+    /// Get without waiting. Returns None if the queue is empty.
+    pub fn try_get<C: MsgqSyscalls>(&self) -> Option<T> {
+        self.get_timeout::<C>(crate::K_NO_WAIT).ok()
+    }
+
+    /// Copy the message at the head of the queue without removing it. Returns None if the queue
+    /// is empty.
+    pub fn peek<C: MsgqSyscalls>(&self) -> Option<T> {
+        let mut msg = MaybeUninit::<T>::uninit();
+        unsafe { C::k_msgq_peek(self.kobj(), msg.as_mut_ptr() as *mut c_void) }
+            .neg_err()
+            .ok()
+            .map(|_| unsafe { msg.assume_init() })
+    }
+
+    /// Discard all messages. Threads waiting to put are released with `Error::NoMsg`.
+    pub fn purge<C: MsgqSyscalls>(&self) {
+        C::k_msgq_purge(self.kobj())
+    }
+
+    /// Number of messages in the queue
+    pub fn num_used<C: MsgqSyscalls>(&self) -> u32 {
+        C::k_msgq_num_used_get(self.kobj())
+    }
+
+    /// Number of free message slots in the queue
+    pub fn num_free<C: MsgqSyscalls>(&self) -> u32 {
+        C::k_msgq_num_free_get(self.kobj())
+    }
+}
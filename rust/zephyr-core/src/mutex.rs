@@ -6,6 +6,8 @@ use zephyr_sys::raw::{k_mutex, k_objects};
 
 use super::NegErr;
 use crate::kobj::*;
+use crate::time::Timeout;
+use crate::Error;
 
 // Declare the Zephyr struct to be a kernel object
 unsafe impl KObj for k_mutex {
@@ -34,6 +36,13 @@ pub trait RawMutex {
     unsafe fn lock<C: MutexSyscalls>(self);
     unsafe fn unlock<C: MutexSyscalls>(self);
     unsafe fn try_lock<C: MutexSyscalls>(self) -> bool;
+    /// Lock with infinite timeout, returning any error instead of panicking
+    unsafe fn checked_lock<C: MutexSyscalls>(self) -> Result<(), Error>;
+    /// Lock without waiting. Returns false if the mutex is held by another thread.
+    unsafe fn checked_try_lock<C: MutexSyscalls>(self) -> Result<bool, Error>;
+    /// Lock, waiting up to `timeout`. Fails with `Error::Again` on timeout, or `Error::Busy` if
+    /// no waiting was requested.
+    unsafe fn lock_timeout<C: MutexSyscalls>(self, timeout: Timeout) -> Result<(), Error>;
 }
 
 impl<'a> RawMutex for &'a KMutex {
@@ -43,9 +52,7 @@ impl<'a> RawMutex for &'a KMutex {
     }
 
     unsafe fn lock<C: MutexSyscalls>(self) {
-        C::k_mutex_lock(self as *const _ as *mut _, zephyr_sys::raw::K_FOREVER)
-            .neg_err()
-            .expect("mutex lock");
+        self.checked_lock::<C>().expect("mutex lock");
     }
 
     unsafe fn unlock<C: MutexSyscalls>(self) {
@@ -53,12 +60,25 @@ impl<'a> RawMutex for &'a KMutex {
     }
 
     unsafe fn try_lock<C: MutexSyscalls>(self) -> bool {
-        match C::k_mutex_lock(self as *const _ as *mut _, zephyr_sys::raw::K_NO_WAIT).neg_err() {
-            Ok(_) => Ok(true),
-            Err(zephyr_sys::raw::EBUSY) => Ok(false),
+        self.checked_try_lock::<C>().expect("mutex try_lock")
+    }
+
+    unsafe fn checked_lock<C: MutexSyscalls>(self) -> Result<(), Error> {
+        self.lock_timeout::<C>(crate::K_FOREVER)
+    }
+
+    unsafe fn checked_try_lock<C: MutexSyscalls>(self) -> Result<bool, Error> {
+        match self.lock_timeout::<C>(crate::K_NO_WAIT) {
+            Ok(()) => Ok(true),
+            Err(Error::Busy) => Ok(false),
             Err(e) => Err(e),
         }
-        .expect("mutex try_lock")
+    }
+
+    unsafe fn lock_timeout<C: MutexSyscalls>(self, timeout: Timeout) -> Result<(), Error> {
+        C::k_mutex_lock(self as *const _ as *mut _, timeout.0)
+            .neg_err()
+            .map(|_| ())
     }
 }
 
@@ -72,6 +92,10 @@ pub struct Mutex<'m, T> {
     data: MutexData<T>,
 }
 
+// The kernel does all locking. Like std, the data only needs to be Send to be shared.
+unsafe impl<'m, T: Send> Send for Mutex<'m, T> {}
+unsafe impl<'m, T: Send> Sync for Mutex<'m, T> {}
+
 impl<'m, T> Mutex<'m, T> {
     pub const unsafe fn new(mutex: &'m KMutex, data: T) -> Self {
         Mutex {
@@ -84,13 +108,43 @@ impl<'m, T> Mutex<'m, T> {
         self.mutex as *const _ as *mut _
     }
 
+    /// The kernel object, e.g. to grant a thread access to it
+    pub fn kmutex(&self) -> &'m KMutex {
+        self.mutex
+    }
+
     pub fn lock<'a, C: MutexSyscalls>(&'a self) -> MutexGuard<'a, T, C> {
         unsafe {
             self.mutex.lock::<C>();
         }
+        self.guard()
+    }
+
+    /// Lock with infinite timeout, returning any error instead of panicking
+    pub fn checked_lock<'a, C: MutexSyscalls>(&'a self) -> Result<MutexGuard<'a, T, C>, Error> {
+        unsafe { self.mutex.checked_lock::<C>()? };
+        Ok(self.guard())
+    }
+
+    /// Lock without waiting. Fails with `Error::Busy` if the mutex is held by another thread.
+    pub fn try_lock<'a, C: MutexSyscalls>(&'a self) -> Result<MutexGuard<'a, T, C>, Error> {
+        self.lock_timeout(crate::K_NO_WAIT)
+    }
+
+    /// Lock, waiting up to `timeout`. Fails with `Error::Again` on timeout.
+    pub fn lock_timeout<'a, C: MutexSyscalls>(
+        &'a self,
+        timeout: Timeout,
+    ) -> Result<MutexGuard<'a, T, C>, Error> {
+        unsafe { self.mutex.lock_timeout::<C>(timeout)? };
+        Ok(self.guard())
+    }
+
+    fn guard<'a, C: MutexSyscalls>(&'a self) -> MutexGuard<'a, T, C> {
         MutexGuard {
             mutex: self,
             _syscalls: PhantomData,
+            _not_send: PhantomData,
         }
     }
 }
@@ -106,9 +160,21 @@ impl<'m, 'd, T> Clone for Mutex<'m, &'d T> {
     }
 }
 
+/// Only the locking thread may unlock a k_mutex, so the guard can't be sent to another thread
 pub struct MutexGuard<'a, T: 'a, C: MutexSyscalls> {
     mutex: &'a Mutex<'a, T>,
     _syscalls: PhantomData<C>,
+    _not_send: PhantomData<*const ()>,
+}
+
+// Sharing the guard only shares &T, like std
+unsafe impl<'a, T: Sync + 'a, C: MutexSyscalls> Sync for MutexGuard<'a, T, C> {}
+
+impl<'a, T: 'a, C: MutexSyscalls> MutexGuard<'a, T, C> {
+    /// The locked kobject, e.g. for a condition variable to release while waiting
+    pub(crate) fn kmutex(&self) -> &'a KMutex {
+        self.mutex.mutex
+    }
 }
 
 impl<'a, T: 'a, C: MutexSyscalls> Drop for MutexGuard<'a, T, C> {
@@ -4,11 +4,21 @@ use core::ops::Deref;
 use core::ptr::NonNull;
 
 use crate::mutex::*;
+use crate::Error;
+
+#[cfg(mutex_pool)]
+pub use mutex_pool::{dump_pool_stats, pool_stats, PoolStats};
 
 pub struct DynMutex(NonNull<KMutex>);
 
 impl DynMutex {
+    /// Returns None if the mutex pool is exhausted
     pub fn new<C: MutexSyscalls>() -> Option<Self> {
+        Self::try_new::<C>().ok()
+    }
+
+    /// Fails with `Error::NoMemory` if the mutex pool is exhausted
+    pub fn try_new<C: MutexSyscalls>() -> Result<Self, Error> {
         unsafe {
             #[cfg(not(mutex_pool))]
             let m = {
@@ -17,9 +27,9 @@ impl DynMutex {
                 m
             };
             #[cfg(mutex_pool)]
-            let m = mutex_pool::alloc_mutex().expect("mutex pool exhausted");
+            let m = mutex_pool::alloc_mutex().ok_or(Error::NoMemory)?;
 
-            Some(DynMutex(NonNull::new_unchecked(m)))
+            Ok(DynMutex(NonNull::new_unchecked(m)))
         }
     }
 
@@ -56,7 +66,9 @@ impl Deref for DynMutex {
 #[cfg(mutex_pool)]
 mod mutex_pool {
     use crate::mutex::*;
-    use core::sync::atomic::{AtomicU8, Ordering};
+    use bitmap_alloc::{words_for, BitmapAlloc};
+    use core::fmt;
+    use core::sync::atomic::{AtomicUsize, Ordering};
 
     const NUM_MUTEX: usize = zephyr_sys::raw::CONFIG_RUST_MUTEX_POOL_SIZE as usize;
 
@@ -65,43 +77,99 @@ mod mutex_pool {
         static rust_mutex_pool: [KMutex; NUM_MUTEX];
     }
 
-    const NUM_USED: usize = (NUM_MUTEX + 7) / 8;
-    /// Bitfield tracking allocated mutexes
-    static USED: [AtomicU8; NUM_USED] = unsafe { core::mem::transmute([0u8; NUM_USED]) };
+    /// Tracks allocated mutexes
+    static POOL: BitmapAlloc<{ words_for(NUM_MUTEX) }> = BitmapAlloc::new(NUM_MUTEX);
+
+    static IN_USE: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+    static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+    static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+    /// Usage of the mutex pool since boot, for sizing CONFIG_RUST_MUTEX_POOL_SIZE
+    #[derive(Clone, Copy, Debug)]
+    pub struct PoolStats {
+        /// CONFIG_RUST_MUTEX_POOL_SIZE
+        pub size: usize,
+        /// Mutexes currently allocated
+        pub in_use: usize,
+        /// Most mutexes ever allocated at once
+        pub peak: usize,
+        /// Successful allocations
+        pub allocations: usize,
+        /// Allocations that found the pool exhausted
+        pub failures: usize,
+    }
+
+    impl fmt::Display for PoolStats {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "Rust mutex pool: {}/{} in use, peak {}, {} allocations, {} failures",
+                self.in_use, self.size, self.peak, self.allocations, self.failures
+            )
+        }
+    }
+
+    pub fn pool_stats() -> PoolStats {
+        PoolStats {
+            size: NUM_MUTEX,
+            in_use: IN_USE.load(Ordering::Relaxed),
+            peak: PEAK.load(Ordering::Relaxed),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            failures: FAILURES.load(Ordering::Relaxed),
+        }
+    }
+
+    struct StrOut;
+
+    impl fmt::Write for StrOut {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            crate::any::k_str_out(s);
+            Ok(())
+        }
+    }
+
+    /// Print the pool statistics to the console
+    pub fn dump_pool_stats() {
+        use core::fmt::Write;
+
+        let _ = writeln!(StrOut, "{}", pool_stats());
+    }
 
     pub fn alloc_mutex() -> Option<*mut KMutex> {
-        let mut ret = None;
-
-        for (i, byte) in USED.iter().enumerate() {
-            // Valid bits in this byte
-            let valid = core::cmp::max(NUM_MUTEX - i * 8, 8);
-            if byte
-                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |val| {
-                    ret = None;
-                    for bit in 0..valid {
-                        let mask = 1 << bit;
-                        if val & mask == 0 {
-                            unsafe {
-                                ret = Some(&rust_mutex_pool[i * 8 + bit] as *const _ as *mut _)
-                            };
-                            return Some(val | mask);
-                        }
-                    }
-                    None
-                })
-                .is_ok()
-            {
-                break;
+        let ret = alloc_slot();
+        match ret {
+            Some(_) => {
+                let in_use = IN_USE.fetch_add(1, Ordering::Relaxed) + 1;
+                PEAK.fetch_max(in_use, Ordering::Relaxed);
+                ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            }
+            None => {
+                FAILURES.fetch_add(1, Ordering::Relaxed);
+                #[cfg(mutex_pool_dump)]
+                dump_pool_stats();
             }
         }
-
         ret
     }
 
     pub fn free_mutex(mutex: *mut KMutex) {
-        let index = unsafe { mutex.offset_from(&rust_mutex_pool[0] as *const _) } as usize;
-        let byte = index / 8;
-        let bit = index % 8;
-        USED[byte].fetch_and(!(1 << bit), Ordering::Relaxed);
+        free_slot(mutex);
+        IN_USE.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn alloc_slot() -> Option<*mut KMutex> {
+        POOL.alloc()
+            .map(|i| unsafe { &rust_mutex_pool[i] as *const _ as *mut _ })
+    }
+
+    fn free_slot(mutex: *mut KMutex) {
+        let index = unsafe { mutex.offset_from(&rust_mutex_pool[0] as *const _) };
+        assert!(
+            index >= 0 && (index as usize) < NUM_MUTEX,
+            "mutex not from pool"
+        );
+        let freed = POOL.free(index as usize);
+        debug_assert!(freed, "mutex pool double free");
     }
 }
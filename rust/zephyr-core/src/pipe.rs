@@ -0,0 +1,117 @@
+use libc::{c_int, c_void};
+use zephyr_sys::raw::{k_objects, k_pipe, k_timeout_t};
+
+use crate::kobj::*;
+
+// Declare the Zephyr struct to be a kernel object
+unsafe impl KObj for k_pipe {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_PIPE;
+}
+
+pub use zephyr_sys::raw::k_pipe as KPipe;
+
+crate::make_static_wrapper!(k_pipe, zephyr_sys::raw::k_pipe);
+
+/// Raw syscall API
+pub trait PipeSyscalls {
+    unsafe fn k_pipe_alloc_init(pipe: &k_pipe, size: usize) -> c_int;
+    unsafe fn k_pipe_put(
+        pipe: &k_pipe,
+        data: *const c_void,
+        bytes_to_write: usize,
+        bytes_written: &mut usize,
+        min_xfer: usize,
+        timeout: k_timeout_t,
+    ) -> c_int;
+    unsafe fn k_pipe_get(
+        pipe: &k_pipe,
+        data: *mut c_void,
+        bytes_to_read: usize,
+        bytes_read: &mut usize,
+        min_xfer: usize,
+        timeout: k_timeout_t,
+    ) -> c_int;
+    #[cfg(zephyr250)]
+    fn k_pipe_read_avail(pipe: &k_pipe) -> usize;
+    #[cfg(zephyr250)]
+    fn k_pipe_write_avail(pipe: &k_pipe) -> usize;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl PipeSyscalls for $context_struct {
+            unsafe fn k_pipe_alloc_init(pipe: &k_pipe, size: usize) -> c_int {
+                zephyr_sys::syscalls::$context::k_pipe_alloc_init(pipe as *const _ as *mut _, size)
+            }
+
+            unsafe fn k_pipe_put(
+                pipe: &k_pipe,
+                data: *const c_void,
+                bytes_to_write: usize,
+                bytes_written: &mut usize,
+                min_xfer: usize,
+                timeout: k_timeout_t,
+            ) -> c_int {
+                zephyr_sys::syscalls::$context::k_pipe_put(
+                    pipe as *const _ as *mut _,
+                    data as *mut _,
+                    bytes_to_write,
+                    bytes_written,
+                    min_xfer,
+                    timeout,
+                )
+            }
+
+            unsafe fn k_pipe_get(
+                pipe: &k_pipe,
+                data: *mut c_void,
+                bytes_to_read: usize,
+                bytes_read: &mut usize,
+                min_xfer: usize,
+                timeout: k_timeout_t,
+            ) -> c_int {
+                zephyr_sys::syscalls::$context::k_pipe_get(
+                    pipe as *const _ as *mut _,
+                    data,
+                    bytes_to_read,
+                    bytes_read,
+                    min_xfer,
+                    timeout,
+                )
+            }
+
+            #[cfg(zephyr250)]
+            fn k_pipe_read_avail(pipe: &k_pipe) -> usize {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_pipe_read_avail(pipe as *const _ as *mut _)
+                }
+            }
+
+            #[cfg(zephyr250)]
+            fn k_pipe_write_avail(pipe: &k_pipe) -> usize {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_pipe_write_avail(pipe as *const _ as *mut _)
+                }
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+/// API implemented on the pipe struct that is not a syscall
+pub trait RawPipe {
+    /// Initialize the pipe to use `buffer` of `size` bytes as its ring buffer. A size of zero
+    /// makes an unbuffered pipe where every transfer is directly between a reader and a writer.
+    ///
+    /// This is not a system call. Only usable from kernel mode.
+    unsafe fn init(&self, buffer: *mut u8, size: usize);
+}
+
+impl RawPipe for k_pipe {
+    unsafe fn init(&self, buffer: *mut u8, size: usize) {
+        zephyr_sys::raw::k_pipe_init(self as *const _ as *mut _, buffer, size)
+    }
+}
@@ -1,13 +1,15 @@
 use libc::{c_int, c_void};
 use zephyr_sys::raw::{
-    _poll_types_bits__POLL_TYPE_SEM_AVAILABLE, _poll_types_bits__POLL_TYPE_SIGNAL, k_poll_event,
-    k_poll_modes_K_POLL_MODE_NOTIFY_ONLY, k_timeout_t, K_POLL_STATE_NOT_READY, K_POLL_TYPE_IGNORE,
+    _poll_types_bits__POLL_TYPE_DATA_AVAILABLE, _poll_types_bits__POLL_TYPE_SEM_AVAILABLE,
+    _poll_types_bits__POLL_TYPE_SIGNAL, k_poll_event, k_poll_modes_K_POLL_MODE_NOTIFY_ONLY,
+    k_timeout_t, K_POLL_STATE_NOT_READY, K_POLL_TYPE_IGNORE,
 };
 
 use crate::kobj::*;
+use crate::queue::KQueue;
 use crate::semaphore::KSem;
 use crate::time::Timeout;
-use crate::NegErr;
+use crate::{Error, NegErr};
 
 pub use crate::poll_signal::*;
 
@@ -25,6 +27,11 @@ unsafe impl PollableKobj for KPollSignal {
     const POLL_TYPE: u32 = 1 << (_poll_types_bits__POLL_TYPE_SIGNAL - 1);
 }
 
+/// K_POLL_TYPE_FIFO_DATA_AVAILABLE. Ready when the queue is not empty.
+unsafe impl PollableKobj for KQueue {
+    const POLL_TYPE: u32 = 1 << (_poll_types_bits__POLL_TYPE_DATA_AVAILABLE - 1);
+}
+
 #[repr(u32)]
 pub enum PollMode {
     NotifyOnly = k_poll_modes_K_POLL_MODE_NOTIFY_ONLY,
@@ -98,41 +105,55 @@ trait_impl!(kernel, crate::context::Kernel);
 trait_impl!(user, crate::context::User);
 trait_impl!(any, crate::context::Any);
 
-#[derive(Clone, Copy, Debug)]
-pub enum PollError {
-    Canceled,
-}
-
 pub trait PollEventsFuncs {
-    fn poll<C: PollSyscalls>(&mut self) -> Result<(), PollError>;
+    /// Fails with `Error::Interrupted` if the wait was canceled
+    fn poll<C: PollSyscalls>(&mut self) -> Result<(), Error>;
     /// Returns true if events are ready, false if timeout.
-    fn poll_timeout<C: PollSyscalls>(
+    fn poll_timeout<C: PollSyscalls>(&mut self, timeout: Option<Timeout>) -> Result<bool, Error>;
+    /// Like `poll`, returning any error instead of panicking, e.g. `Error::NoMemory` if the
+    /// thread's resource pool is exhausted in user mode
+    fn checked_poll<C: PollSyscalls>(&mut self) -> Result<(), Error>;
+    /// Like `poll_timeout`, returning any error instead of panicking
+    fn checked_poll_timeout<C: PollSyscalls>(
         &mut self,
         timeout: Option<Timeout>,
-    ) -> Result<bool, PollError>;
+    ) -> Result<bool, Error>;
 }
 
 impl PollEventsFuncs for [KPollEvent] {
-    fn poll<C: PollSyscalls>(&mut self) -> Result<(), PollError> {
-        match C::k_poll(self, zephyr_sys::raw::K_FOREVER).neg_err() {
-            Ok(_) => Ok(()),
-            Err(zephyr_sys::raw::EINTR) => Err(PollError::Canceled),
-            Err(zephyr_sys::raw::ENOMEM) => panic!("k_poll OOM"),
+    fn poll<C: PollSyscalls>(&mut self) -> Result<(), Error> {
+        match self.checked_poll::<C>() {
+            Err(Error::Interrupted) => Err(Error::Interrupted),
+            Err(Error::NoMemory) => panic!("k_poll OOM"),
             Err(e) => panic!("k_poll error {}", e),
+            ok => ok,
         }
     }
 
-    fn poll_timeout<C: PollSyscalls>(
+    fn poll_timeout<C: PollSyscalls>(&mut self, timeout: Option<Timeout>) -> Result<bool, Error> {
+        match self.checked_poll_timeout::<C>(timeout) {
+            Err(Error::Interrupted) => Err(Error::Interrupted),
+            Err(Error::NoMemory) => panic!("k_poll OOM"),
+            Err(e) => panic!("k_poll error {}", e),
+            ok => ok,
+        }
+    }
+
+    fn checked_poll<C: PollSyscalls>(&mut self) -> Result<(), Error> {
+        C::k_poll(self, zephyr_sys::raw::K_FOREVER)
+            .neg_err()
+            .map(|_| ())
+    }
+
+    fn checked_poll_timeout<C: PollSyscalls>(
         &mut self,
         timeout: Option<Timeout>,
-    ) -> Result<bool, PollError> {
+    ) -> Result<bool, Error> {
         let timeout = timeout.map(|x| x.0).unwrap_or(zephyr_sys::raw::K_FOREVER);
         match C::k_poll(self, timeout).neg_err() {
             Ok(_) => Ok(true),
-            Err(zephyr_sys::raw::EAGAIN) => Ok(false),
-            Err(zephyr_sys::raw::EINTR) => Err(PollError::Canceled),
-            Err(zephyr_sys::raw::ENOMEM) => panic!("k_poll OOM"),
-            Err(e) => panic!("k_poll error {}", e),
+            Err(Error::Again) => Ok(false),
+            Err(e) => Err(e),
         }
     }
 }
@@ -0,0 +1,282 @@
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+use core::ptr;
+
+use libc::{c_int, c_void};
+use zephyr_sys::raw::{k_objects, k_queue, k_timeout_t};
+
+use super::NegErr;
+use crate::kobj::*;
+use crate::time::Timeout;
+
+// Declare the Zephyr struct to be a kernel object. k_fifo and k_lifo are just a k_queue with a
+// different insertion order, so only the k_queue is a kernel object.
+unsafe impl KObj for k_queue {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_QUEUE;
+}
+
+pub use zephyr_sys::raw::k_queue as KQueue;
+
+crate::make_static_wrapper!(k_queue, zephyr_sys::raw::k_queue);
+
+/// Raw syscall API
+pub trait QueueSyscalls {
+    unsafe fn k_queue_init(queue: &k_queue);
+    fn k_queue_cancel_wait(queue: &k_queue);
+    unsafe fn k_queue_alloc_append(queue: &k_queue, data: *mut c_void) -> c_int;
+    unsafe fn k_queue_alloc_prepend(queue: &k_queue, data: *mut c_void) -> c_int;
+    fn k_queue_get(queue: &k_queue, timeout: k_timeout_t) -> *mut c_void;
+    fn k_queue_is_empty(queue: &k_queue) -> c_int;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl QueueSyscalls for $context_struct {
+            unsafe fn k_queue_init(queue: &k_queue) {
+                zephyr_sys::syscalls::$context::k_queue_init(queue as *const _ as *mut _)
+            }
+
+            fn k_queue_cancel_wait(queue: &k_queue) {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_queue_cancel_wait(queue as *const _ as *mut _)
+                }
+            }
+
+            unsafe fn k_queue_alloc_append(queue: &k_queue, data: *mut c_void) -> c_int {
+                zephyr_sys::syscalls::$context::k_queue_alloc_append(
+                    queue as *const _ as *mut _,
+                    data,
+                ) as c_int
+            }
+
+            unsafe fn k_queue_alloc_prepend(queue: &k_queue, data: *mut c_void) -> c_int {
+                zephyr_sys::syscalls::$context::k_queue_alloc_prepend(
+                    queue as *const _ as *mut _,
+                    data,
+                ) as c_int
+            }
+
+            fn k_queue_get(queue: &k_queue, timeout: k_timeout_t) -> *mut c_void {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_queue_get(
+                        queue as *const _ as *mut _,
+                        timeout,
+                    )
+                }
+            }
+
+            fn k_queue_is_empty(queue: &k_queue) -> c_int {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_queue_is_empty(queue as *const _ as *mut _)
+                }
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+/// Intrusive insertion. These are not system calls, so are only implemented for kernel mode.
+pub trait QueueKernelAPI {
+    unsafe fn k_queue_append(queue: &k_queue, data: *mut c_void);
+    unsafe fn k_queue_prepend(queue: &k_queue, data: *mut c_void);
+}
+
+impl QueueKernelAPI for crate::context::Kernel {
+    unsafe fn k_queue_append(queue: &k_queue, data: *mut c_void) {
+        zephyr_sys::raw::k_queue_append(queue as *const _ as *mut _, data)
+    }
+
+    unsafe fn k_queue_prepend(queue: &k_queue, data: *mut c_void) {
+        zephyr_sys::raw::k_queue_prepend(queue as *const _ as *mut _, data)
+    }
+}
+
+/// What is actually linked into the kernel queue
+///
+/// The kernel reserves the first word of each item for its list pointer. Boxing the item in a
+/// node keeps that word out of `T`, and the item itself is never copied.
+#[repr(C)]
+struct Node<T> {
+    _reserved: *mut c_void,
+    item: Box<T>,
+}
+
+impl<T> Node<T> {
+    fn new(item: Box<T>) -> *mut c_void {
+        Box::into_raw(Box::new(Node {
+            _reserved: ptr::null_mut(),
+            item,
+        })) as *mut c_void
+    }
+
+    unsafe fn into_item(node: *mut c_void) -> Box<T> {
+        Box::from_raw(node as *mut Node<T>).item
+    }
+}
+
+/// Queue passing ownership of `Box<T>` between threads
+///
+/// Creating this is unsafe because every item in the kernel queue must have been inserted by a
+/// `Queue<T>` of the same type. Items still in the queue when it is no longer used are leaked.
+pub struct Queue<'a, T> {
+    queue: *const KQueue,
+    _marker: PhantomData<(&'a KQueue, Box<T>)>,
+}
+
+// The kernel does all locking. Items may be sent to another thread.
+unsafe impl<'a, T: Send> Send for Queue<'a, T> {}
+unsafe impl<'a, T: Send> Sync for Queue<'a, T> {}
+
+impl<'a, T> Clone for Queue<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Queue<'a, T> {}
+
+impl<'a, T> Queue<'a, T> {
+    pub const unsafe fn new(queue: &'a KQueue) -> Self {
+        Self::from_ptr(queue)
+    }
+
+    /// Like `new`, but usable in a static initializer where the kobject is only available as a
+    /// pointer to uninitialized storage.
+    pub const unsafe fn from_ptr(queue: *const KQueue) -> Self {
+        Queue {
+            queue,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the underlying kobject, e.g. to grant a thread access to it or to poll on it.
+    pub fn kobj(&self) -> &'a KQueue {
+        unsafe { &*self.queue }
+    }
+
+    /// Insert at the tail
+    pub fn append<C: QueueKernelAPI>(&self, item: Box<T>) {
+        unsafe { C::k_queue_append(self.kobj(), Node::new(item)) }
+    }
+
+    /// Insert at the head
+    pub fn prepend<C: QueueKernelAPI>(&self, item: Box<T>) {
+        unsafe { C::k_queue_prepend(self.kobj(), Node::new(item)) }
+    }
+
+    /// Insert at the tail from any context. The kernel allocates its list entry from the calling
+    /// thread's resource pool. On failure, the item is returned.
+    pub fn alloc_append<C: QueueSyscalls>(&self, item: Box<T>) -> Result<(), Box<T>> {
+        let node = Node::<T>::new(item);
+        match unsafe { C::k_queue_alloc_append(self.kobj(), node) }.neg_err() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(unsafe { Node::into_item(node) }),
+        }
+    }
+
+    /// Insert at the head from any context. See `alloc_append`.
+    pub fn alloc_prepend<C: QueueSyscalls>(&self, item: Box<T>) -> Result<(), Box<T>> {
+        let node = Node::<T>::new(item);
+        match unsafe { C::k_queue_alloc_prepend(self.kobj(), node) }.neg_err() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(unsafe { Node::into_item(node) }),
+        }
+    }
+
+    /// Remove the item at the head, waiting up to `timeout`. Returns None on timeout or if
+    /// `cancel_wait` was called.
+    pub fn get<C: QueueSyscalls>(&self, timeout: Timeout) -> Option<Box<T>> {
+        let node = C::k_queue_get(self.kobj(), timeout.0);
+        if node.is_null() {
+            None
+        } else {
+            Some(unsafe { Node::into_item(node) })
+        }
+    }
+
+    /// Remove the item at the head without waiting
+    pub fn try_get<C: QueueSyscalls>(&self) -> Option<Box<T>> {
+        self.get::<C>(crate::K_NO_WAIT)
+    }
+
+    pub fn is_empty<C: QueueSyscalls>(&self) -> bool {
+        C::k_queue_is_empty(self.kobj()) != 0
+    }
+
+    /// Wake the first thread waiting in `get`, which returns None
+    pub fn cancel_wait<C: QueueSyscalls>(&self) {
+        C::k_queue_cancel_wait(self.kobj())
+    }
+}
+
+/// First in, first out queue of `Box<T>`. Same as k_fifo.
+pub struct Fifo<'a, T>(Queue<'a, T>);
+
+/// Last in, first out queue of `Box<T>`. Same as k_lifo.
+pub struct Lifo<'a, T>(Queue<'a, T>);
+
+macro_rules! ordered_queue_impl {
+    ($name:ident, $insert:ident, $alloc_insert:ident) => {
+        impl<'a, T> Clone for $name<'a, T> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<'a, T> Copy for $name<'a, T> {}
+
+        impl<'a, T> $name<'a, T> {
+            /// Unsafe for the same reasons as `Queue::new`
+            pub const unsafe fn new(queue: &'a KQueue) -> Self {
+                $name(Queue::new(queue))
+            }
+
+            pub const unsafe fn from_ptr(queue: *const KQueue) -> Self {
+                $name(Queue::from_ptr(queue))
+            }
+
+            pub fn queue(&self) -> Queue<'a, T> {
+                self.0
+            }
+
+            pub fn kobj(&self) -> &'a KQueue {
+                self.0.kobj()
+            }
+
+            /// Insert an item. Not a system call, so kernel mode only.
+            pub fn push<C: QueueKernelAPI>(&self, item: Box<T>) {
+                self.0.$insert::<C>(item)
+            }
+
+            /// Insert an item from any context. See `Queue::alloc_append`.
+            pub fn push_alloc<C: QueueSyscalls>(&self, item: Box<T>) -> Result<(), Box<T>> {
+                self.0.$alloc_insert::<C>(item)
+            }
+
+            /// Remove an item, waiting up to `timeout`. Returns None on timeout or if
+            /// `cancel_wait` was called.
+            pub fn pop<C: QueueSyscalls>(&self, timeout: Timeout) -> Option<Box<T>> {
+                self.0.get::<C>(timeout)
+            }
+
+            /// Remove an item without waiting
+            pub fn try_pop<C: QueueSyscalls>(&self) -> Option<Box<T>> {
+                self.0.try_get::<C>()
+            }
+
+            pub fn is_empty<C: QueueSyscalls>(&self) -> bool {
+                self.0.is_empty::<C>()
+            }
+
+            pub fn cancel_wait<C: QueueSyscalls>(&self) {
+                self.0.cancel_wait::<C>()
+            }
+        }
+    };
+}
+
+ordered_queue_impl!(Fifo, append, alloc_append);
+ordered_queue_impl!(Lifo, prepend, alloc_prepend);
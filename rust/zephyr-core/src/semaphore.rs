@@ -3,6 +3,7 @@ use zephyr_sys::raw::{k_objects, k_sem, k_timeout_t};
 use super::NegErr;
 use crate::kobj::*;
 use crate::time::Timeout;
+use crate::Error;
 
 // Declare the Zephyr struct to be a kernel object
 unsafe impl KObj for k_sem {
@@ -69,6 +70,13 @@ pub trait Semaphore {
     fn take_timeout<C: SemaphoreSyscalls>(&self, timeout: Timeout) -> bool;
     /// Take with no timeout. Returns true if successful.
     fn try_take<C: SemaphoreSyscalls>(&self) -> bool;
+    /// Take with infinite timeout, returning any error instead of panicking. Fails with
+    /// `Error::Again` if the semaphore is reset while waiting.
+    fn checked_take<C: SemaphoreSyscalls>(&self) -> Result<(), Error>;
+    /// Like `take_timeout`, returning any other error instead of panicking
+    fn checked_take_timeout<C: SemaphoreSyscalls>(&self, timeout: Timeout) -> Result<bool, Error>;
+    /// Like `try_take`, returning any other error instead of panicking
+    fn checked_try_take<C: SemaphoreSyscalls>(&self) -> Result<bool, Error>;
     fn give<C: SemaphoreSyscalls>(&self);
     fn reset<C: SemaphoreSyscalls>(&self);
     fn count<C: SemaphoreSyscalls>(&self) -> u32;
@@ -80,28 +88,37 @@ impl Semaphore for k_sem {
     }
 
     fn take<C: SemaphoreSyscalls>(&self) {
+        self.checked_take::<C>().expect("sem take");
+    }
+
+    fn take_timeout<C: SemaphoreSyscalls>(&self, timeout: Timeout) -> bool {
+        self.checked_take_timeout::<C>(timeout).expect("sem take")
+    }
+
+    fn try_take<C: SemaphoreSyscalls>(&self) -> bool {
+        self.checked_try_take::<C>().expect("sem take")
+    }
+
+    fn checked_take<C: SemaphoreSyscalls>(&self) -> Result<(), Error> {
         C::k_sem_take(self, zephyr_sys::raw::K_FOREVER.into())
             .neg_err()
-            .expect("sem take");
+            .map(|_| ())
     }
 
-    fn take_timeout<C: SemaphoreSyscalls>(&self, timeout: Timeout) -> bool {
+    fn checked_take_timeout<C: SemaphoreSyscalls>(&self, timeout: Timeout) -> Result<bool, Error> {
         match C::k_sem_take(self, timeout.0).neg_err() {
             Ok(_) => Ok(true),
-            Err(zephyr_sys::raw::EBUSY) => Ok(false),
-            Err(zephyr_sys::raw::EAGAIN) => Ok(false),
+            Err(Error::Busy) | Err(Error::Again) => Ok(false),
             Err(e) => Err(e),
         }
-        .expect("sem take")
     }
 
-    fn try_take<C: SemaphoreSyscalls>(&self) -> bool {
+    fn checked_try_take<C: SemaphoreSyscalls>(&self) -> Result<bool, Error> {
         match C::k_sem_take(self, (zephyr_sys::raw::K_NO_WAIT).into()).neg_err() {
             Ok(_) => Ok(true),
-            Err(zephyr_sys::raw::EBUSY) => Ok(false),
+            Err(Error::Busy) => Ok(false),
             Err(e) => Err(e),
         }
-        .expect("sem take")
     }
 
     fn give<C: SemaphoreSyscalls>(&self) {
@@ -0,0 +1,264 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+use libc::{c_int, c_void};
+use zephyr_sys::raw::{k_mem_slab, k_objects, k_timeout_t};
+
+use super::NegErr;
+use crate::kobj::*;
+use crate::time::Timeout;
+use crate::Error;
+
+// Declare the Zephyr struct to be a kernel object
+unsafe impl KObj for k_mem_slab {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_MEM_SLAB;
+}
+
+pub use zephyr_sys::raw::k_mem_slab as KMemSlab;
+
+crate::make_static_wrapper!(k_mem_slab, zephyr_sys::raw::k_mem_slab);
+
+/// Slab API. These are not system calls, so are only implemented for kernel mode.
+pub trait MemSlabKernelAPI {
+    unsafe fn k_mem_slab_init(
+        slab: &k_mem_slab,
+        buffer: *mut c_void,
+        block_size: usize,
+        num_blocks: u32,
+    ) -> c_int;
+    unsafe fn k_mem_slab_alloc(
+        slab: &k_mem_slab,
+        mem: *mut *mut c_void,
+        timeout: k_timeout_t,
+    ) -> c_int;
+    unsafe fn k_mem_slab_free(slab: &k_mem_slab, mem: *mut *mut c_void);
+}
+
+impl MemSlabKernelAPI for crate::context::Kernel {
+    unsafe fn k_mem_slab_init(
+        slab: &k_mem_slab,
+        buffer: *mut c_void,
+        block_size: usize,
+        num_blocks: u32,
+    ) -> c_int {
+        zephyr_sys::raw::k_mem_slab_init(
+            slab as *const _ as *mut _,
+            buffer,
+            block_size as _,
+            num_blocks as _,
+        )
+    }
+
+    unsafe fn k_mem_slab_alloc(
+        slab: &k_mem_slab,
+        mem: *mut *mut c_void,
+        timeout: k_timeout_t,
+    ) -> c_int {
+        zephyr_sys::raw::k_mem_slab_alloc(slab as *const _ as *mut _, mem, timeout)
+    }
+
+    unsafe fn k_mem_slab_free(slab: &k_mem_slab, mem: *mut *mut c_void) {
+        zephyr_sys::raw::k_mem_slab_free(slab as *const _ as *mut _, mem)
+    }
+}
+
+/// API implemented on the slab struct
+///
+/// The slab and its buffer are kernel memory, so this is only usable from kernel mode.
+pub trait MemSlab {
+    /// Initialize the slab to hand out `num_blocks` blocks of `block_size` bytes from `buffer`
+    unsafe fn init<C: MemSlabKernelAPI>(
+        &self,
+        buffer: *mut u8,
+        block_size: usize,
+        num_blocks: u32,
+    ) -> Result<(), Error>;
+
+    /// Take a block, waiting up to `timeout` for one to be freed. Fails with `Error::NoMemory`
+    /// with no wait or `Error::Again` on timeout.
+    unsafe fn alloc<C: MemSlabKernelAPI>(&self, timeout: Timeout) -> Result<NonNull<u8>, Error>;
+
+    /// Return a block from `alloc`
+    unsafe fn free<C: MemSlabKernelAPI>(&self, block: NonNull<u8>);
+
+    fn num_used<C: MemSlabKernelAPI>(&self) -> u32;
+
+    fn num_free<C: MemSlabKernelAPI>(&self) -> u32;
+}
+
+impl MemSlab for k_mem_slab {
+    unsafe fn init<C: MemSlabKernelAPI>(
+        &self,
+        buffer: *mut u8,
+        block_size: usize,
+        num_blocks: u32,
+    ) -> Result<(), Error> {
+        C::k_mem_slab_init(self, buffer as *mut _, block_size, num_blocks)
+            .neg_err()
+            .map(|_| ())
+    }
+
+    unsafe fn alloc<C: MemSlabKernelAPI>(&self, timeout: Timeout) -> Result<NonNull<u8>, Error> {
+        let mut block: *mut c_void = ptr::null_mut();
+        C::k_mem_slab_alloc(self, &mut block, timeout.0).neg_err()?;
+        Ok(NonNull::new_unchecked(block as *mut u8))
+    }
+
+    unsafe fn free<C: MemSlabKernelAPI>(&self, block: NonNull<u8>) {
+        let mut block = block.as_ptr() as *mut c_void;
+        C::k_mem_slab_free(self, &mut block)
+    }
+
+    // k_mem_slab_num_used_get is inline, so not in the bindings
+    fn num_used<C: MemSlabKernelAPI>(&self) -> u32 {
+        unsafe { ptr::read_volatile(&self.num_used) as u32 }
+    }
+
+    fn num_free<C: MemSlabKernelAPI>(&self) -> u32 {
+        self.num_blocks as u32 - self.num_used::<C>()
+    }
+}
+
+/// One block of a slab of `T`. Big enough and aligned for both a `T` and the kernel's free list
+/// pointer.
+#[repr(C)]
+pub union SlabBlock<T> {
+    _value: ManuallyDrop<T>,
+    _next: *mut u8,
+}
+
+/// Slab whose blocks each hold a `T`. Kernel mode only.
+///
+/// Creating this is unsafe because the kernel object must have been initialized with blocks of
+/// `size_of::<SlabBlock<T>>()` bytes, aligned for `SlabBlock<T>`.
+pub struct Slab<'a, T> {
+    slab: *const KMemSlab,
+    _marker: PhantomData<(&'a KMemSlab, T)>,
+}
+
+// The kernel does all locking. Values may be allocated in one thread and dropped in another.
+unsafe impl<'a, T: Send> Send for Slab<'a, T> {}
+unsafe impl<'a, T: Send> Sync for Slab<'a, T> {}
+
+impl<'a, T> Clone for Slab<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Slab<'a, T> {}
+
+impl<'a, T> Slab<'a, T> {
+    /// Block size to initialize the kernel object with
+    pub const BLOCK_SIZE: usize = mem::size_of::<SlabBlock<T>>();
+
+    pub const unsafe fn new(slab: &'a KMemSlab) -> Self {
+        Self::from_ptr(slab)
+    }
+
+    /// Like `new`, but usable in a static initializer where the kobject is only available as a
+    /// pointer to uninitialized storage.
+    pub const unsafe fn from_ptr(slab: *const KMemSlab) -> Self {
+        Slab {
+            slab,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the underlying kobject
+    pub fn kobj(&self) -> &'a KMemSlab {
+        unsafe { &*self.slab }
+    }
+
+    /// Move `value` into a block, waiting forever for one to be free
+    pub fn alloc<C: MemSlabKernelAPI>(&self, value: T) -> SlabBox<'a, T, C> {
+        self.alloc_timeout(value, crate::K_FOREVER)
+            .expect("slab alloc with K_FOREVER")
+    }
+
+    /// Move `value` into a block if one is free. Fails with `Error::NoMemory`.
+    pub fn try_alloc<C: MemSlabKernelAPI>(&self, value: T) -> Result<SlabBox<'a, T, C>, Error> {
+        self.alloc_timeout(value, crate::K_NO_WAIT)
+    }
+
+    /// Move `value` into a block, waiting up to `timeout`. Fails with `Error::Again` on timeout.
+    pub fn alloc_timeout<C: MemSlabKernelAPI>(
+        &self,
+        value: T,
+        timeout: Timeout,
+    ) -> Result<SlabBox<'a, T, C>, Error> {
+        unsafe {
+            let block = self.kobj().alloc::<C>(timeout)?.cast::<T>();
+            block.as_ptr().write(value);
+            Ok(SlabBox {
+                value: block,
+                slab: self.kobj(),
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    /// Number of blocks allocated
+    pub fn num_used<C: MemSlabKernelAPI>(&self) -> u32 {
+        self.kobj().num_used::<C>()
+    }
+
+    /// Number of blocks available
+    pub fn num_free<C: MemSlabKernelAPI>(&self) -> u32 {
+        self.kobj().num_free::<C>()
+    }
+}
+
+/// A `T` in a slab block. The block is returned to the slab on drop.
+pub struct SlabBox<'a, T, C: MemSlabKernelAPI> {
+    value: NonNull<T>,
+    slab: &'a KMemSlab,
+    _marker: PhantomData<(T, C)>,
+}
+
+unsafe impl<'a, T: Send, C: MemSlabKernelAPI> Send for SlabBox<'a, T, C> {}
+unsafe impl<'a, T: Sync, C: MemSlabKernelAPI> Sync for SlabBox<'a, T, C> {}
+
+impl<'a, T, C: MemSlabKernelAPI> SlabBox<'a, T, C> {
+    /// Move the value out, freeing the block
+    pub fn into_inner(b: Self) -> T {
+        let b = ManuallyDrop::new(b);
+        unsafe {
+            let value = b.value.as_ptr().read();
+            b.slab.free::<C>(b.value.cast());
+            value
+        }
+    }
+}
+
+impl<'a, T, C: MemSlabKernelAPI> Deref for SlabBox<'a, T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<'a, T, C: MemSlabKernelAPI> DerefMut for SlabBox<'a, T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<'a, T, C: MemSlabKernelAPI> Drop for SlabBox<'a, T, C> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.value.as_ptr());
+            self.slab.free::<C>(self.value.cast());
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug, C: MemSlabKernelAPI> fmt::Debug for SlabBox<'a, T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
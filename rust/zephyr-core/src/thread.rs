@@ -1,11 +1,42 @@
-use core::ptr::NonNull;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::alloc::Layout;
+use core::cell::UnsafeCell;
+use core::ffi::CStr;
+use core::marker::PhantomData;
+use core::ptr::{self, NonNull};
 
-use crate::kobj::KObj;
+use libc::{c_char, c_int, c_void};
+use zephyr_sys::raw::{k_objects, k_thread, k_thread_entry_t, k_thread_stack_t, k_timeout_t};
+
+use super::NegErr;
+use crate::kobj::*;
+use crate::time::Timeout;
+use crate::Error;
+
+// Declare the Zephyr struct to be a kernel object
+unsafe impl KObj for k_thread {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_THREAD;
+}
+
+pub use zephyr_sys::raw::k_thread as KThread;
+pub use zephyr_sys::raw::k_thread_stack_t as KThreadStack;
+
+crate::make_static_wrapper!(k_thread, zephyr_sys::raw::k_thread);
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ThreadId(NonNull<zephyr_sys::raw::k_thread>);
 
+// Only an identifier. All access goes through the kernel.
+unsafe impl Send for ThreadId {}
+unsafe impl Sync for ThreadId {}
+
 impl ThreadId {
+    /// Unsafe because `thread` must be a valid thread object
+    pub const unsafe fn from_ptr(thread: *const KThread) -> Self {
+        ThreadId(NonNull::new_unchecked(thread as *mut _))
+    }
+
     pub fn tid(&self) -> zephyr_sys::raw::k_tid_t {
         self.0.as_ptr()
     }
@@ -17,12 +48,47 @@ impl ThreadId {
     pub fn k_object_access_grant<C: ThreadSyscalls, K: KObj>(&self, kobj: &K) {
         C::k_object_access_grant(kobj, *self)
     }
+
+    pub fn abort<C: ThreadSyscalls>(&self) {
+        C::k_thread_abort(*self)
+    }
+
+    pub fn suspend<C: ThreadSyscalls>(&self) {
+        C::k_thread_suspend(*self)
+    }
+
+    pub fn resume<C: ThreadSyscalls>(&self) {
+        C::k_thread_resume(*self)
+    }
+
+    /// Start a thread created with a delay of K_FOREVER
+    pub fn start<C: ThreadSyscalls>(&self) {
+        C::k_thread_start(*self)
+    }
 }
 
 pub trait ThreadSyscalls {
     fn k_wakeup(thread: ThreadId);
     fn k_current_get() -> crate::thread::ThreadId;
     fn k_object_access_grant<K: KObj>(kobj: &K, thread: ThreadId);
+    unsafe fn k_thread_create(
+        new_thread: *mut k_thread,
+        stack: *mut k_thread_stack_t,
+        stack_size: usize,
+        entry: k_thread_entry_t,
+        p1: *mut c_void,
+        p2: *mut c_void,
+        p3: *mut c_void,
+        prio: c_int,
+        options: u32,
+        delay: k_timeout_t,
+    ) -> ThreadId;
+    fn k_thread_start(thread: ThreadId);
+    fn k_thread_join(thread: ThreadId, timeout: k_timeout_t) -> c_int;
+    fn k_thread_abort(thread: ThreadId);
+    fn k_thread_suspend(thread: ThreadId);
+    fn k_thread_resume(thread: ThreadId);
+    fn k_thread_name_set(thread: ThreadId, name: &'static CStr) -> c_int;
 }
 
 macro_rules! trait_impl {
@@ -45,9 +111,7 @@ macro_rules! trait_impl {
                     #[no_mangle]
                     static z_tls_current: *mut zephyr_sys::raw::k_thread;
                 }
-                ThreadId(unsafe {
-                    NonNull::new_unchecked(z_tls_current)
-                })
+                ThreadId(unsafe { NonNull::new_unchecked(z_tls_current) })
             }
 
             #[cfg(all(zephyr270, not(tls)))]
@@ -69,6 +133,51 @@ macro_rules! trait_impl {
                     );
                 }
             }
+
+            unsafe fn k_thread_create(
+                new_thread: *mut k_thread,
+                stack: *mut k_thread_stack_t,
+                stack_size: usize,
+                entry: k_thread_entry_t,
+                p1: *mut c_void,
+                p2: *mut c_void,
+                p3: *mut c_void,
+                prio: c_int,
+                options: u32,
+                delay: k_timeout_t,
+            ) -> ThreadId {
+                ThreadId(NonNull::new_unchecked(
+                    zephyr_sys::syscalls::$context::k_thread_create(
+                        new_thread, stack, stack_size, entry, p1, p2, p3, prio, options, delay,
+                    ),
+                ))
+            }
+
+            fn k_thread_start(thread: ThreadId) {
+                unsafe { zephyr_sys::syscalls::$context::k_thread_start(thread.tid()) }
+            }
+
+            fn k_thread_join(thread: ThreadId, timeout: k_timeout_t) -> c_int {
+                unsafe { zephyr_sys::syscalls::$context::k_thread_join(thread.tid(), timeout) }
+            }
+
+            fn k_thread_abort(thread: ThreadId) {
+                unsafe { zephyr_sys::syscalls::$context::k_thread_abort(thread.tid()) }
+            }
+
+            fn k_thread_suspend(thread: ThreadId) {
+                unsafe { zephyr_sys::syscalls::$context::k_thread_suspend(thread.tid()) }
+            }
+
+            fn k_thread_resume(thread: ThreadId) {
+                unsafe { zephyr_sys::syscalls::$context::k_thread_resume(thread.tid()) }
+            }
+
+            fn k_thread_name_set(thread: ThreadId, name: &'static CStr) -> c_int {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_thread_name_set(thread.tid(), name.as_ptr())
+                }
+            }
         }
     };
 }
@@ -76,3 +185,437 @@ macro_rules! trait_impl {
 trait_impl!(kernel, crate::context::Kernel);
 trait_impl!(user, crate::context::User);
 trait_impl!(any, crate::context::Any);
+
+/// k_thread options
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct ThreadOptions(pub u32);
+
+impl ThreadOptions {
+    /// K_ESSENTIAL. The system panics if the thread exits or aborts.
+    pub const ESSENTIAL: Self = ThreadOptions(1 << 0);
+    /// K_FP_REGS. The thread uses the floating point registers.
+    pub const FP_REGS: Self = ThreadOptions(1 << 1);
+    /// K_USER. The thread is created in user mode.
+    pub const USER: Self = ThreadOptions(1 << 2);
+    /// K_INHERIT_PERMS. The thread inherits the kernel object permissions of its creator.
+    pub const INHERIT_PERMS: Self = ThreadOptions(1 << 3);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for ThreadOptions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        ThreadOptions(self.0 | rhs.0)
+    }
+}
+
+/// Alignment and guard area reserved for a heap allocated stack. Generous enough for the MPU
+/// guard on ARM. Heap stacks are rejected with CONFIG_USERSPACE, CONFIG_HW_STACK_PROTECTION or
+/// CONFIG_MPU_REQUIRES_POWER_OF_TWO_ALIGNMENT, where the kernel would change the permissions of
+/// whole pages or regions around the stack.
+const HEAP_STACK_ALIGN: usize = 64;
+const HEAP_STACK_RESERVED: usize = 256;
+pub const HEAP_STACK_SUPPORTED: bool = cfg!(not(heap_stack_unsupported));
+
+enum Stack {
+    Static(*mut k_thread_stack_t, usize),
+    Heap(usize),
+}
+
+enum ThreadStorage {
+    Static(&'static KThread),
+    Heap,
+}
+
+/// Where a spawned thread's resources live, so they can be freed after it exits
+struct Resources {
+    thread: Option<Box<KThread>>,
+    stack: Option<(*mut u8, Layout)>,
+}
+
+impl Resources {
+    /// Free anything heap allocated. The thread must have exited.
+    unsafe fn free(self) {
+        drop(self.thread);
+        if let Some((ptr, layout)) = self.stack {
+            alloc::alloc::dealloc(ptr, layout);
+        }
+    }
+}
+
+const DEFAULT_STACK_SIZE: usize = 1024;
+
+/// K_LOWEST_APPLICATION_THREAD_PRIO
+pub const LOWEST_APPLICATION_PRIORITY: i32 =
+    zephyr_sys::raw::CONFIG_NUM_PREEMPT_PRIORITIES as i32 - 1;
+
+/// Thread factory, like that in std
+///
+/// By default, the thread object and a stack of 1024 bytes are allocated from the heap and freed
+/// when the `JoinHandle` is dropped after the thread exits. A user mode thread needs a thread
+/// object and stack known to the kernel, so must use `thread_static` and `stack_static`. The same
+/// goes for any thread's stack with CONFIG_USERSPACE, CONFIG_HW_STACK_PROTECTION or
+/// CONFIG_MPU_REQUIRES_POWER_OF_TWO_ALIGNMENT, else `spawn` fails with `Error::NotSupported`.
+pub struct Builder {
+    thread: ThreadStorage,
+    stack: Stack,
+    priority: i32,
+    options: ThreadOptions,
+    name: Option<&'static CStr>,
+    cpu_mask: Option<u32>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder {
+            thread: ThreadStorage::Heap,
+            stack: Stack::Heap(DEFAULT_STACK_SIZE),
+            priority: LOWEST_APPLICATION_PRIORITY,
+            options: ThreadOptions::default(),
+            name: None,
+            cpu_mask: None,
+        }
+    }
+
+    /// Allocate a stack of `size` bytes from the heap
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack = Stack::Heap(size);
+        self
+    }
+
+    /// Use a stack defined elsewhere, e.g. by K_THREAD_STACK_DEFINE in C.
+    ///
+    /// Unsafe because the stack must be `size` bytes as passed to the define and must not be in
+    /// use by any other thread for as long as this one runs.
+    pub unsafe fn stack_static(mut self, stack: *mut KThreadStack, size: usize) -> Self {
+        self.stack = Stack::Static(stack, size);
+        self
+    }
+
+    /// Use a statically allocated thread object.
+    ///
+    /// Unsafe because the object must not be in use by any other thread.
+    pub unsafe fn thread_static(mut self, thread: &'static KThread) -> Self {
+        self.thread = ThreadStorage::Static(thread);
+        self
+    }
+
+    /// Negative priorities are cooperative, others are preemptible
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn options(mut self, options: ThreadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the thread name. Ignored without CONFIG_THREAD_NAME.
+    pub fn name(mut self, name: &'static CStr) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Only run on the CPUs set in `mask`. Requires CONFIG_SCHED_CPU_MASK and kernel mode, else
+    /// `spawn` fails with `Error::NotSupported`.
+    pub fn cpu_mask(mut self, mask: u32) -> Self {
+        self.cpu_mask = Some(mask);
+        self
+    }
+
+    /// Create and start the thread running `f`
+    ///
+    /// Fails with `Error::NotSupported` for a heap allocated stack where the kernel can't use one,
+    /// a user mode thread with any heap allocated storage, or a CPU mask set from user mode, and
+    /// `Error::NoMemory` if the heap is exhausted.
+    pub fn spawn<C, F, T>(self, f: F) -> Result<JoinHandle<T, C>, Error>
+    where
+        C: ThreadSyscalls,
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let heap_thread = matches!(self.thread, ThreadStorage::Heap);
+        let heap_stack = matches!(self.stack, Stack::Heap(_));
+        if heap_stack && !HEAP_STACK_SUPPORTED {
+            return Err(Error::NotSupported);
+        }
+        if self.options.contains(ThreadOptions::USER) && (heap_thread || heap_stack) {
+            return Err(Error::NotSupported);
+        }
+        // The CPU mask calls are not system calls
+        if self.cpu_mask.is_some() && crate::is_user_context() {
+            return Err(Error::NotSupported);
+        }
+
+        let mut resources = Resources {
+            thread: None,
+            stack: None,
+        };
+        let thread = match self.thread {
+            ThreadStorage::Static(thread) => thread as *const _ as *mut KThread,
+            ThreadStorage::Heap => {
+                // Allocate directly rather than with Box::new, which aborts on failure
+                let ptr = unsafe { alloc::alloc::alloc_zeroed(Layout::new::<KThread>()) };
+                if ptr.is_null() {
+                    return Err(Error::NoMemory);
+                }
+                let thread = resources
+                    .thread
+                    .insert(unsafe { Box::from_raw(ptr as *mut KThread) });
+                &mut **thread as *mut KThread
+            }
+        };
+        let (stack, stack_size) = match self.stack {
+            Stack::Static(stack, size) => (stack, size),
+            Stack::Heap(size) => {
+                let layout = Layout::from_size_align(size + HEAP_STACK_RESERVED, HEAP_STACK_ALIGN)
+                    .map_err(|_| Error::Invalid)?;
+                let ptr = unsafe { alloc::alloc::alloc(layout) };
+                if ptr.is_null() {
+                    return Err(Error::NoMemory);
+                }
+                resources.stack = Some((ptr, layout));
+                (ptr as *mut k_thread_stack_t, size)
+            }
+        };
+
+        let packet = Arc::new(Packet(UnsafeCell::new(None)));
+        let their_packet = packet.clone();
+        let main: Box<dyn FnOnce()> = Box::new(move || {
+            let result = f();
+            unsafe { *their_packet.0.get() = Some(result) };
+        });
+        // Double box to pass a thin pointer
+        let main = Box::into_raw(Box::new(main));
+
+        // Hold the thread until it is fully configured
+        let delay = if self.cpu_mask.is_some() {
+            zephyr_sys::raw::K_FOREVER
+        } else {
+            zephyr_sys::raw::K_NO_WAIT
+        };
+        let tid = unsafe {
+            C::k_thread_create(
+                thread,
+                stack,
+                stack_size,
+                Some(thread_main),
+                main as *mut c_void,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                self.priority,
+                self.options.0,
+                delay,
+            )
+        };
+
+        if let Some(name) = self.name {
+            // Fails only if thread names are not enabled
+            C::k_thread_name_set(tid, name);
+        }
+
+        if let Some(mask) = self.cpu_mask {
+            if let Err(e) = set_cpu_mask(tid, mask) {
+                C::k_thread_abort(tid);
+                unsafe {
+                    drop(Box::from_raw(main));
+                    resources.free();
+                }
+                return Err(e);
+            }
+            C::k_thread_start(tid);
+        }
+
+        Ok(JoinHandle {
+            thread: tid,
+            packet,
+            resources: Some(resources),
+            _syscalls: PhantomData,
+        })
+    }
+}
+
+#[cfg(sched_cpu_mask)]
+fn set_cpu_mask(thread: ThreadId, mask: u32) -> Result<(), Error> {
+    unsafe {
+        zephyr_sys::raw::k_thread_cpu_mask_clear(thread.tid()).neg_err()?;
+        for cpu in 0..32 {
+            if mask & (1 << cpu) != 0 {
+                zephyr_sys::raw::k_thread_cpu_mask_enable(thread.tid(), cpu).neg_err()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(sched_cpu_mask))]
+fn set_cpu_mask(_thread: ThreadId, _mask: u32) -> Result<(), Error> {
+    Err(Error::NotSupported)
+}
+
+unsafe extern "C" fn thread_main(p1: *mut c_void, _p2: *mut c_void, _p3: *mut c_void) {
+    let main = Box::from_raw(p1 as *mut Box<dyn FnOnce()>);
+    main();
+}
+
+/// Return value of the thread, written before it exits and read after it is joined
+struct Packet<T>(UnsafeCell<Option<T>>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinError {
+    /// The thread did not exit in time
+    TimedOut,
+    /// The thread tried to join itself, or threads tried to join each other
+    Deadlock,
+    /// The thread was aborted before returning, or its result was already taken
+    Aborted,
+    /// Any other error code returned by the kernel
+    Other(Error),
+}
+
+/// Owned permission to join a thread
+///
+/// If the handle is dropped while the thread is running, the thread is detached and its heap
+/// allocated resources are leaked.
+pub struct JoinHandle<T, C: ThreadSyscalls> {
+    thread: ThreadId,
+    packet: Arc<Packet<T>>,
+    resources: Option<Resources>,
+    _syscalls: PhantomData<C>,
+}
+
+unsafe impl<T: Send, C: ThreadSyscalls> Send for JoinHandle<T, C> {}
+unsafe impl<T: Send, C: ThreadSyscalls> Sync for JoinHandle<T, C> {}
+
+impl<T, C: ThreadSyscalls> JoinHandle<T, C> {
+    pub fn thread(&self) -> ThreadId {
+        self.thread
+    }
+
+    /// Wait for the thread to exit and return its result
+    pub fn join(mut self) -> Result<T, JoinError> {
+        self.join_timeout(crate::K_FOREVER)
+    }
+
+    /// Wait up to `timeout` for the thread to exit and return its result
+    pub fn join_timeout(&mut self, timeout: Timeout) -> Result<T, JoinError> {
+        match C::k_thread_join(self.thread, timeout.0).neg_err() {
+            Ok(_) => (),
+            Err(Error::Busy) | Err(Error::Again) => return Err(JoinError::TimedOut),
+            Err(Error::Deadlock) => return Err(JoinError::Deadlock),
+            Err(e) => return Err(JoinError::Other(e)),
+        }
+        // The thread has exited, so nothing else accesses the packet
+        unsafe { (*self.packet.0.get()).take() }.ok_or(JoinError::Aborted)
+    }
+
+    pub fn abort(&self) {
+        self.thread.abort::<C>()
+    }
+
+    pub fn suspend(&self) {
+        self.thread.suspend::<C>()
+    }
+
+    pub fn resume(&self) {
+        self.thread.resume::<C>()
+    }
+}
+
+impl<T, C: ThreadSyscalls> Drop for JoinHandle<T, C> {
+    fn drop(&mut self) {
+        if let Some(resources) = self.resources.take() {
+            if C::k_thread_join(self.thread, zephyr_sys::raw::K_NO_WAIT) == 0 {
+                unsafe { resources.free() };
+            } else {
+                // Still running. Detach.
+                core::mem::forget(resources);
+            }
+        }
+    }
+}
+
+/// Stack area of a thread defined with k_thread_define!. `S` is an aligned array of `KThreadStack`
+/// generated by the macro. It is not wrapped in `MaybeUninit`, a union, so that the kernel's
+/// object list sees an array of stack elements and registers it as a stack object.
+pub struct StaticThreadStack<S>(UnsafeCell<S>);
+
+// Only the thread running on the stack accesses it
+unsafe impl<S> Sync for StaticThreadStack<S> {}
+
+impl<S> StaticThreadStack<S> {
+    /// The stack is expected to be in a noinit section, so `stack` is not actually loaded
+    pub const fn new(stack: S) -> Self {
+        StaticThreadStack(UnsafeCell::new(stack))
+    }
+
+    pub const fn as_ptr(&self) -> *mut KThreadStack {
+        self.0.get() as *mut KThreadStack
+    }
+}
+
+/// Entry of a user thread defined with k_thread_define!. The thread starts in kernel mode to join
+/// the Rust memory domain, then drops to user mode to run `entry`.
+pub fn user_thread_main(entry: fn()) -> ! {
+    #[cfg(usermode)]
+    {
+        use crate::context::Kernel as C;
+        crate::static_mem_domain!(rust_std_domain)
+            .add_thread::<C>(C::k_current_get())
+            .expect("add thread to rust_std_domain");
+    }
+    crate::kernel::k_thread_user_mode_enter(entry)
+}
+
+/// Record read by the kernel at boot to create a thread defined with k_thread_define!. Must be
+/// placed in the `._static_thread_data.static.*` section.
+#[repr(transparent)]
+pub struct StaticThreadData(zephyr_sys::raw::_static_thread_data);
+
+// Only read by the kernel
+unsafe impl Sync for StaticThreadData {}
+
+impl StaticThreadData {
+    /// Like Z_THREAD_INITIALIZER. A delay of -1 (SYS_FOREVER_MS) creates the thread without
+    /// starting it.
+    ///
+    /// Unsafe because the thread object and stack must be used by this thread only, and `name`
+    /// must be a nul terminated string.
+    #[allow(clippy::too_many_arguments)]
+    pub const unsafe fn new(
+        thread: *const KThread,
+        stack: *mut KThreadStack,
+        stack_size: usize,
+        entry: unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void),
+        priority: i32,
+        options: ThreadOptions,
+        delay_ms: i32,
+        name: *const c_char,
+    ) -> Self {
+        StaticThreadData(zephyr_sys::raw::_static_thread_data {
+            init_thread: thread as *mut _,
+            init_stack: stack,
+            init_stack_size: stack_size as _,
+            init_entry: Some(entry),
+            init_p1: ptr::null_mut(),
+            init_p2: ptr::null_mut(),
+            init_p3: ptr::null_mut(),
+            init_prio: priority,
+            init_options: options.0,
+            init_delay: delay_ms,
+            init_abort: None,
+            init_name: name,
+        })
+    }
+}
@@ -0,0 +1,288 @@
+use alloc::boxed::Box;
+use core::ops::Deref;
+use core::time::Duration;
+
+use libc::c_void;
+use zephyr_sys::raw::{k_objects, k_ticks_t, k_timeout_t, k_timer};
+
+use crate::kobj::*;
+use crate::time::{Ticks, Timeout};
+
+// Declare the Zephyr struct to be a kernel object
+unsafe impl KObj for k_timer {
+    const OTYPE: k_objects = zephyr_sys::raw::k_objects_K_OBJ_TIMER;
+}
+
+pub use zephyr_sys::raw::k_timer as KTimer;
+
+crate::make_static_wrapper!(k_timer, zephyr_sys::raw::k_timer);
+
+/// Raw syscall API
+pub trait TimerSyscalls {
+    fn k_timer_start(timer: &k_timer, duration: k_timeout_t, period: k_timeout_t);
+    fn k_timer_stop(timer: &k_timer);
+    fn k_timer_status_get(timer: &k_timer) -> u32;
+    fn k_timer_status_sync(timer: &k_timer) -> u32;
+    fn k_timer_remaining_ticks(timer: &k_timer) -> k_ticks_t;
+    unsafe fn k_timer_user_data_set(timer: &k_timer, user_data: *mut c_void);
+    fn k_timer_user_data_get(timer: &k_timer) -> *mut c_void;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl TimerSyscalls for $context_struct {
+            fn k_timer_start(timer: &k_timer, duration: k_timeout_t, period: k_timeout_t) {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_timer_start(
+                        timer as *const _ as *mut _,
+                        duration,
+                        period,
+                    )
+                }
+            }
+
+            fn k_timer_stop(timer: &k_timer) {
+                unsafe { zephyr_sys::syscalls::$context::k_timer_stop(timer as *const _ as *mut _) }
+            }
+
+            fn k_timer_status_get(timer: &k_timer) -> u32 {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_timer_status_get(timer as *const _ as *mut _)
+                }
+            }
+
+            fn k_timer_status_sync(timer: &k_timer) -> u32 {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_timer_status_sync(timer as *const _ as *mut _)
+                }
+            }
+
+            fn k_timer_remaining_ticks(timer: &k_timer) -> k_ticks_t {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_timer_remaining_ticks(
+                        timer as *const _ as *mut _,
+                    )
+                }
+            }
+
+            unsafe fn k_timer_user_data_set(timer: &k_timer, user_data: *mut c_void) {
+                zephyr_sys::syscalls::$context::k_timer_user_data_set(
+                    timer as *const _ as *mut _,
+                    user_data,
+                )
+            }
+
+            fn k_timer_user_data_get(timer: &k_timer) -> *mut c_void {
+                unsafe {
+                    zephyr_sys::syscalls::$context::k_timer_user_data_get(
+                        timer as *const _ as *mut _,
+                    )
+                }
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+pub type KTimerFn = unsafe extern "C" fn(timer: *mut k_timer);
+
+/// API implemented on the timer struct that is not a syscall
+pub trait RawTimer {
+    /// Initialize the timer with optional expiry and stop functions.
+    ///
+    /// This is not a system call. Only usable from kernel mode.
+    unsafe fn init(&self, expiry: Option<KTimerFn>, stop: Option<KTimerFn>);
+}
+
+impl RawTimer for k_timer {
+    unsafe fn init(&self, expiry: Option<KTimerFn>, stop: Option<KTimerFn>) {
+        zephyr_sys::raw::k_timer_init(self as *const _ as *mut _, expiry, stop)
+    }
+}
+
+/// Handle to an initialized timer. Usable from any context with access to the kobject.
+#[derive(Clone, Copy)]
+pub struct Timer<'t> {
+    timer: &'t KTimer,
+}
+
+// The kernel does all locking
+unsafe impl<'t> Send for Timer<'t> {}
+unsafe impl<'t> Sync for Timer<'t> {}
+
+impl<'t> Timer<'t> {
+    pub const fn new(timer: &'t KTimer) -> Self {
+        Timer { timer }
+    }
+
+    pub fn kobj(&self) -> &'t KTimer {
+        self.timer
+    }
+
+    /// Start or restart the timer. It first expires after `duration`, then every `period`. A
+    /// period of `K_NO_WAIT` or `K_FOREVER` makes a one-shot timer.
+    pub fn start<C: TimerSyscalls>(&self, duration: Timeout, period: Timeout) {
+        C::k_timer_start(self.timer, duration.0, period.0)
+    }
+
+    pub fn stop<C: TimerSyscalls>(&self) {
+        C::k_timer_stop(self.timer)
+    }
+
+    /// Number of expirations since the status was last read. Resets the count.
+    pub fn status_get<C: TimerSyscalls>(&self) -> u32 {
+        C::k_timer_status_get(self.timer)
+    }
+
+    /// Block until the timer expires or is stopped. Returns the number of expirations since the
+    /// status was last read, which is zero if it was stopped. Resets the count.
+    pub fn status_sync<C: TimerSyscalls>(&self) -> u32 {
+        C::k_timer_status_sync(self.timer)
+    }
+
+    /// Time until the next expiration. Zero if the timer is stopped.
+    pub fn remaining_get<C: TimerSyscalls>(&self) -> Duration {
+        Ticks(C::k_timer_remaining_ticks(self.timer)).into()
+    }
+}
+
+/// The timer passed to expiry and stop handlers
+///
+/// Expiry handlers are called by the kernel from the system clock interrupt, so only the
+/// operations that never block are available here.
+pub struct TimerIsr<'t> {
+    timer: &'t KTimer,
+}
+
+impl<'t> TimerIsr<'t> {
+    pub fn kobj(&self) -> &'t KTimer {
+        self.timer
+    }
+
+    pub fn start(&self, duration: Timeout, period: Timeout) {
+        Timer::new(self.timer).start::<crate::context::Kernel>(duration, period)
+    }
+
+    pub fn stop(&self) {
+        Timer::new(self.timer).stop::<crate::context::Kernel>()
+    }
+
+    pub fn status_get(&self) -> u32 {
+        Timer::new(self.timer).status_get::<crate::context::Kernel>()
+    }
+
+    pub fn remaining_get(&self) -> Duration {
+        Timer::new(self.timer).remaining_get::<crate::context::Kernel>()
+    }
+}
+
+struct Handlers<E, S> {
+    expiry: E,
+    stop: Option<S>,
+}
+
+unsafe extern "C" fn expiry_trampoline<E, S>(timer: *mut k_timer)
+where
+    E: FnMut(&TimerIsr) + Send + 'static,
+{
+    use crate::context::Kernel as C;
+
+    let timer = &*timer;
+    let handlers = C::k_timer_user_data_get(timer) as *mut Handlers<E, S>;
+    ((*handlers).expiry)(&TimerIsr { timer })
+}
+
+unsafe extern "C" fn stop_trampoline<E, S>(timer: *mut k_timer)
+where
+    S: FnMut(&TimerIsr) + Send + 'static,
+{
+    use crate::context::Kernel as C;
+
+    let timer = &*timer;
+    let handlers = C::k_timer_user_data_get(timer) as *mut Handlers<E, S>;
+    if let Some(ref mut stop) = (*handlers).stop {
+        stop(&TimerIsr { timer })
+    }
+}
+
+/// Timer calling Rust closures on expiry and stop
+///
+/// The expiry handler runs in interrupt context. The stop handler runs in the context of whoever
+/// stops the timer, which may also be an interrupt. Both are moved into a heap allocation that the
+/// kernel finds through the timer's user data, so they must be `Send`. Dropping this stops the
+/// timer before freeing the handlers.
+pub struct CallbackTimer<'t, E, S> {
+    timer: Timer<'t>,
+    handlers: *mut Handlers<E, S>,
+}
+
+unsafe impl<'t, E: Send, S: Send> Send for CallbackTimer<'t, E, S> {}
+unsafe impl<'t, E: Send, S: Send> Sync for CallbackTimer<'t, E, S> {}
+
+impl<'t, E> CallbackTimer<'t, E, fn(&TimerIsr)>
+where
+    E: FnMut(&TimerIsr) + Send + 'static,
+{
+    /// Initialize `timer` to call `expiry`. Kernel mode only.
+    ///
+    /// Unsafe because the timer must not be in use by anything else: the kernel object is
+    /// reinitialized and its user data is taken over.
+    pub unsafe fn new(timer: &'t KTimer, expiry: E) -> Self {
+        Self::init(timer, expiry, None)
+    }
+}
+
+impl<'t, E, S> CallbackTimer<'t, E, S>
+where
+    E: FnMut(&TimerIsr) + Send + 'static,
+    S: FnMut(&TimerIsr) + Send + 'static,
+{
+    /// Like `new`, but also call `stop` when the timer is stopped before it expires
+    pub unsafe fn with_stop(timer: &'t KTimer, expiry: E, stop: S) -> Self {
+        Self::init(timer, expiry, Some(stop))
+    }
+
+    unsafe fn init(timer: &'t KTimer, expiry: E, stop: Option<S>) -> Self {
+        use crate::context::Kernel as C;
+
+        let has_stop = stop.is_some();
+        let handlers = Box::into_raw(Box::new(Handlers { expiry, stop }));
+        timer.init(
+            Some(expiry_trampoline::<E, S>),
+            if has_stop {
+                Some(stop_trampoline::<E, S>)
+            } else {
+                None
+            },
+        );
+        C::k_timer_user_data_set(timer, handlers as *mut c_void);
+        CallbackTimer {
+            timer: Timer::new(timer),
+            handlers,
+        }
+    }
+}
+
+impl<'t, E, S> Deref for CallbackTimer<'t, E, S> {
+    type Target = Timer<'t>;
+
+    fn deref(&self) -> &Timer<'t> {
+        &self.timer
+    }
+}
+
+impl<'t, E, S> Drop for CallbackTimer<'t, E, S> {
+    fn drop(&mut self) {
+        use crate::context::Kernel as C;
+
+        self.timer.stop::<C>();
+        unsafe {
+            // Nothing may call the trampolines once the handlers are gone
+            self.timer.kobj().init(None, None);
+            drop(Box::from_raw(self.handlers));
+        }
+    }
+}
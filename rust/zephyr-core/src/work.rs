@@ -0,0 +1,574 @@
+use alloc::boxed::Box;
+use core::alloc::Layout;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use libc::c_int;
+use zephyr_sys::raw::{k_thread_stack_t, k_timeout_t, k_work, k_work_delayable, k_work_q};
+
+use super::NegErr;
+use crate::time::Timeout;
+use crate::Error;
+
+pub use zephyr_sys::raw::k_work as KWork;
+pub use zephyr_sys::raw::k_work_delayable as KWorkDelayable;
+pub use zephyr_sys::raw::k_work_q as KWorkQ;
+
+/// Work queue API. These are not system calls, so are only implemented for kernel mode.
+pub trait WorkKernelAPI {
+    unsafe fn k_work_queue_start(
+        queue: &k_work_q,
+        stack: *mut k_thread_stack_t,
+        stack_size: usize,
+        priority: c_int,
+    );
+    unsafe fn k_work_init(work: *mut k_work, handler: KWorkFn);
+    unsafe fn k_work_submit(work: *mut k_work) -> c_int;
+    unsafe fn k_work_submit_to_queue(queue: &k_work_q, work: *mut k_work) -> c_int;
+    unsafe fn k_work_cancel(work: *mut k_work) -> c_int;
+    unsafe fn k_work_flush(work: *mut k_work) -> bool;
+    unsafe fn k_work_busy_get(work: *const k_work) -> c_int;
+    unsafe fn k_work_init_delayable(dwork: *mut k_work_delayable, handler: KWorkFn);
+    unsafe fn k_work_schedule_for_queue(
+        queue: &k_work_q,
+        dwork: *mut k_work_delayable,
+        delay: k_timeout_t,
+    ) -> c_int;
+    unsafe fn k_work_reschedule_for_queue(
+        queue: &k_work_q,
+        dwork: *mut k_work_delayable,
+        delay: k_timeout_t,
+    ) -> c_int;
+    unsafe fn k_work_cancel_delayable(dwork: *mut k_work_delayable) -> c_int;
+    unsafe fn k_work_flush_delayable(dwork: *mut k_work_delayable) -> bool;
+    unsafe fn k_work_delayable_busy_get(dwork: *const k_work_delayable) -> c_int;
+}
+
+impl WorkKernelAPI for crate::context::Kernel {
+    unsafe fn k_work_queue_start(
+        queue: &k_work_q,
+        stack: *mut k_thread_stack_t,
+        stack_size: usize,
+        priority: c_int,
+    ) {
+        let queue = queue as *const _ as *mut _;
+        zephyr_sys::raw::k_work_queue_init(queue);
+        zephyr_sys::raw::k_work_queue_start(queue, stack, stack_size, priority, ptr::null());
+    }
+
+    unsafe fn k_work_init(work: *mut k_work, handler: KWorkFn) {
+        zephyr_sys::raw::k_work_init(work, Some(handler))
+    }
+
+    unsafe fn k_work_submit(work: *mut k_work) -> c_int {
+        zephyr_sys::raw::k_work_submit(work)
+    }
+
+    unsafe fn k_work_submit_to_queue(queue: &k_work_q, work: *mut k_work) -> c_int {
+        zephyr_sys::raw::k_work_submit_to_queue(queue as *const _ as *mut _, work)
+    }
+
+    unsafe fn k_work_cancel(work: *mut k_work) -> c_int {
+        zephyr_sys::raw::k_work_cancel(work)
+    }
+
+    unsafe fn k_work_flush(work: *mut k_work) -> bool {
+        let mut sync = core::mem::MaybeUninit::<zephyr_sys::raw::k_work_sync>::zeroed();
+        zephyr_sys::raw::k_work_flush(work, sync.as_mut_ptr())
+    }
+
+    unsafe fn k_work_busy_get(work: *const k_work) -> c_int {
+        zephyr_sys::raw::k_work_busy_get(work)
+    }
+
+    unsafe fn k_work_init_delayable(dwork: *mut k_work_delayable, handler: KWorkFn) {
+        zephyr_sys::raw::k_work_init_delayable(dwork, Some(handler))
+    }
+
+    unsafe fn k_work_schedule_for_queue(
+        queue: &k_work_q,
+        dwork: *mut k_work_delayable,
+        delay: k_timeout_t,
+    ) -> c_int {
+        zephyr_sys::raw::k_work_schedule_for_queue(queue as *const _ as *mut _, dwork, delay)
+    }
+
+    unsafe fn k_work_reschedule_for_queue(
+        queue: &k_work_q,
+        dwork: *mut k_work_delayable,
+        delay: k_timeout_t,
+    ) -> c_int {
+        zephyr_sys::raw::k_work_reschedule_for_queue(queue as *const _ as *mut _, dwork, delay)
+    }
+
+    unsafe fn k_work_cancel_delayable(dwork: *mut k_work_delayable) -> c_int {
+        zephyr_sys::raw::k_work_cancel_delayable(dwork)
+    }
+
+    unsafe fn k_work_flush_delayable(dwork: *mut k_work_delayable) -> bool {
+        let mut sync = core::mem::MaybeUninit::<zephyr_sys::raw::k_work_sync>::zeroed();
+        zephyr_sys::raw::k_work_flush_delayable(dwork, sync.as_mut_ptr())
+    }
+
+    unsafe fn k_work_delayable_busy_get(dwork: *const k_work_delayable) -> c_int {
+        zephyr_sys::raw::k_work_delayable_busy_get(dwork)
+    }
+}
+
+pub type KWorkFn = unsafe extern "C" fn(work: *mut k_work);
+
+/// Result of successfully submitting or scheduling a work item
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Submitted {
+    /// The item was idle and is now queued or scheduled
+    Queued,
+    /// The item was already pending. For a reschedule, its delay was replaced.
+    AlreadyPending,
+    /// The item was running and has been queued to run again
+    Requeued,
+}
+
+impl Submitted {
+    fn from_rc(rc: c_int) -> Result<Self, Error> {
+        match rc.neg_err()? {
+            0 => Ok(Submitted::AlreadyPending),
+            1 => Ok(Submitted::Queued),
+            _ => Ok(Submitted::Requeued),
+        }
+    }
+}
+
+/// A work queue thread
+#[derive(Clone, Copy)]
+pub struct WorkQueue<'q> {
+    queue: &'q KWorkQ,
+}
+
+// The kernel does all locking
+unsafe impl<'q> Send for WorkQueue<'q> {}
+unsafe impl<'q> Sync for WorkQueue<'q> {}
+
+impl WorkQueue<'static> {
+    /// The system work queue
+    pub fn system() -> Self {
+        WorkQueue {
+            queue: unsafe { &*ptr::addr_of!(zephyr_sys::raw::k_sys_work_q) },
+        }
+    }
+
+    /// Initialize and start `queue`, running its thread on `stack` at `priority`.
+    ///
+    /// Unsafe because the queue must not already be in use and the stack must be a thread stack
+    /// of `stack_size` bytes that is not used by anything else, e.g. from K_KERNEL_STACK_DEFINE.
+    pub unsafe fn start<C: WorkKernelAPI>(
+        queue: &'static KWorkQ,
+        stack: *mut k_thread_stack_t,
+        stack_size: usize,
+        priority: i32,
+    ) -> Self {
+        C::k_work_queue_start(queue, stack, stack_size, priority);
+        WorkQueue { queue }
+    }
+}
+
+impl<'q> WorkQueue<'q> {
+    /// Use a queue that has already been started, e.g. from C
+    pub const unsafe fn new(queue: &'q KWorkQ) -> Self {
+        WorkQueue { queue }
+    }
+
+    pub fn kobj(&self) -> &'q KWorkQ {
+        self.queue
+    }
+
+    /// Run `f` on the queue as soon as possible. The work item is allocated from the heap and
+    /// freed after it has run or been canceled.
+    pub fn submit<C: WorkKernelAPI, F>(&self, f: F) -> Result<WorkHandle<'q, C>, Error>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.schedule(crate::K_NO_WAIT, f)
+    }
+
+    /// Run `f` on the queue after `delay`. Fails with `Error::NoMemory` if the work item can't be
+    /// allocated.
+    pub fn schedule<C: WorkKernelAPI, F>(
+        &self,
+        delay: Timeout,
+        f: F,
+    ) -> Result<WorkHandle<'q, C>, Error>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        reclaim::<C>();
+        // One reference for the handle, one for the kernel until the closure runs
+        let header = WorkNode::<F>::new::<C>(f)? as *const WorkHeader;
+        let work = header;
+        let rc = unsafe { C::k_work_schedule_for_queue(self.queue, (*work).dwork(), delay.0) };
+        match Submitted::from_rc(rc) {
+            Ok(submitted) => Ok(WorkHandle {
+                header,
+                queue: self.queue,
+                submitted,
+                _syscalls: PhantomData,
+            }),
+            Err(e) => {
+                // Never queued, so the kernel holds no pointer. Release both references.
+                unsafe {
+                    ((*work).release)(work);
+                    ((*header).release)(header);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Common prefix of every heap allocated work item. The kernel's k_work is at the start so the
+/// handler can find the rest of the item from the pointer it is given.
+#[repr(C)]
+struct WorkHeader {
+    dwork: UnsafeCell<KWorkDelayable>,
+    /// Set by whichever of the handler or a cancel gets to the closure first
+    claimed: AtomicBool,
+    /// References whose release was deferred because the kernel was still using the item
+    deferred: AtomicUsize,
+    next: AtomicPtr<WorkHeader>,
+    /// References to the containing `WorkNode<F>`
+    refs: AtomicUsize,
+    /// Drop one reference to the containing `WorkNode<F>`, freeing it with the last
+    release: unsafe fn(*const WorkHeader),
+}
+
+impl WorkHeader {
+    fn dwork(&self) -> *mut KWorkDelayable {
+        self.dwork.get()
+    }
+
+    fn is_busy<C: WorkKernelAPI>(&self) -> bool {
+        unsafe { C::k_work_delayable_busy_get(self.dwork()) != 0 }
+    }
+}
+
+#[repr(C)]
+struct WorkNode<F> {
+    header: WorkHeader,
+    f: UnsafeCell<Option<F>>,
+}
+
+impl<F: FnOnce() + Send + 'static> WorkNode<F> {
+    /// Allocate a work item holding two references
+    fn new<C: WorkKernelAPI>(f: F) -> Result<*const Self, Error> {
+        // Allocate directly rather than with Box::new, which aborts on failure
+        let node = unsafe { alloc::alloc::alloc(Layout::new::<Self>()) } as *mut Self;
+        if node.is_null() {
+            return Err(Error::NoMemory);
+        }
+        unsafe {
+            ptr::write(
+                node,
+                WorkNode {
+                    header: WorkHeader {
+                        dwork: UnsafeCell::new(core::mem::zeroed()),
+                        claimed: AtomicBool::new(false),
+                        deferred: AtomicUsize::new(0),
+                        next: AtomicPtr::new(ptr::null_mut()),
+                        refs: AtomicUsize::new(2),
+                        release: Self::release,
+                    },
+                    f: UnsafeCell::new(Some(f)),
+                },
+            );
+            C::k_work_init_delayable((*node).header.dwork(), Self::run);
+        }
+        Ok(node)
+    }
+
+    unsafe fn release(header: *const WorkHeader) {
+        if (*header).refs.fetch_sub(1, Ordering::Release) == 1 {
+            fence(Ordering::Acquire);
+            drop(Box::from_raw(header as *mut Self))
+        }
+    }
+
+    unsafe extern "C" fn run(work: *mut k_work) {
+        let node = &*(work as *const Self);
+        if !node.header.claimed.swap(true, Ordering::SeqCst) {
+            if let Some(f) = (*node.f.get()).take() {
+                f();
+            }
+            // The work queue still touches the item after we return, so it can't be freed here
+            defer_release(&node.header, 1);
+        }
+    }
+}
+
+/// Items waiting to be freed once the kernel is done with them
+static RECLAIM: AtomicPtr<WorkHeader> = AtomicPtr::new(ptr::null_mut());
+
+fn defer_release(header: &WorkHeader, count: usize) {
+    if header.deferred.fetch_add(count, Ordering::SeqCst) != 0 {
+        // Already on the list
+        return;
+    }
+    let header = header as *const _ as *mut WorkHeader;
+    let mut head = RECLAIM.load(Ordering::SeqCst);
+    loop {
+        unsafe { (*header).next.store(head, Ordering::SeqCst) };
+        match RECLAIM.compare_exchange(head, header, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => break,
+            Err(new_head) => head = new_head,
+        }
+    }
+}
+
+/// Release a reference now if the kernel is not using the item, else defer it
+fn release<C: WorkKernelAPI>(header: *const WorkHeader) {
+    let h = unsafe { &*header };
+    if h.is_busy::<C>() {
+        defer_release(h, 1);
+    } else {
+        unsafe { (h.release)(header) };
+    }
+}
+
+/// Free items that are no longer busy. Called whenever items are submitted or handles dropped, so
+/// the list stays short.
+fn reclaim<C: WorkKernelAPI>() {
+    let mut header = RECLAIM.swap(ptr::null_mut(), Ordering::SeqCst);
+    while !header.is_null() {
+        let h = unsafe { &*header };
+        let next = h.next.load(Ordering::SeqCst);
+        let count = h.deferred.swap(0, Ordering::SeqCst);
+        if h.is_busy::<C>() {
+            defer_release(h, count);
+        } else {
+            for _ in 0..count {
+                unsafe { (h.release)(header) };
+            }
+        }
+        header = next;
+    }
+}
+
+/// Handle to a submitted closure
+///
+/// Dropping the handle does not cancel the work.
+pub struct WorkHandle<'q, C: WorkKernelAPI> {
+    /// Holds one reference to the `WorkNode`
+    header: *const WorkHeader,
+    queue: &'q KWorkQ,
+    submitted: Submitted,
+    _syscalls: PhantomData<C>,
+}
+
+// The kernel does all locking and the closure is Send
+unsafe impl<'q, C: WorkKernelAPI> Send for WorkHandle<'q, C> {}
+unsafe impl<'q, C: WorkKernelAPI> Sync for WorkHandle<'q, C> {}
+
+impl<'q, C: WorkKernelAPI> WorkHandle<'q, C> {
+    fn header(&self) -> &WorkHeader {
+        unsafe { &*self.header }
+    }
+
+    /// What the kernel reported when the closure was submitted or scheduled
+    pub fn submitted(&self) -> Submitted {
+        self.submitted
+    }
+
+    /// Whether the work is scheduled, queued or running
+    pub fn is_pending(&self) -> bool {
+        self.header().is_busy::<C>()
+    }
+
+    /// Whether the closure has started, or will never run because it was canceled
+    pub fn is_done(&self) -> bool {
+        self.header().claimed.load(Ordering::SeqCst)
+    }
+
+    /// Prevent the closure from running if it has not started. Returns true if this call
+    /// canceled it. The closure is dropped.
+    pub fn cancel(&self) -> bool {
+        let header = self.header();
+        unsafe { C::k_work_cancel_delayable(header.dwork()) };
+        if header.claimed.swap(true, Ordering::SeqCst) {
+            // Already ran or is running
+            false
+        } else {
+            // The handler will never take the closure. Release the kernel's reference for it.
+            release::<C>(self.header);
+            true
+        }
+    }
+
+    /// Block until the work is no longer pending. Returns true if it had to wait. Must be called
+    /// from a thread, not an interrupt.
+    pub fn flush(&self) -> bool {
+        unsafe { C::k_work_flush_delayable(self.header().dwork()) }
+    }
+
+    /// Change the delay before the closure runs.
+    ///
+    /// Fails with `Error::Already` once the closure has started or been canceled through this handle.
+    pub fn reschedule(&self, delay: Timeout) -> Result<Submitted, Error> {
+        if self.is_done() {
+            return Err(Error::Already);
+        }
+        let rc =
+            unsafe { C::k_work_reschedule_for_queue(self.queue, self.header().dwork(), delay.0) };
+        Submitted::from_rc(rc)
+    }
+}
+
+impl<'q, C: WorkKernelAPI> Drop for WorkHandle<'q, C> {
+    fn drop(&mut self) {
+        release::<C>(self.header);
+        reclaim::<C>();
+    }
+}
+
+/// Statically allocated work item running a plain function, like K_WORK_DEFINE. Can be submitted
+/// any number of times with no allocation. Declare with `k_work_define!`.
+#[repr(C)]
+pub struct StaticWork {
+    work: UnsafeCell<MaybeUninit<KWork>>,
+    handler: UnsafeCell<Option<fn()>>,
+}
+
+unsafe impl Sync for StaticWork {}
+
+impl StaticWork {
+    /// Unsafe because `init` must be called before use, and the item must be static because the
+    /// kernel keeps a pointer to it.
+    pub const unsafe fn uninit() -> Self {
+        StaticWork {
+            work: UnsafeCell::new(MaybeUninit::uninit()),
+            handler: UnsafeCell::new(None),
+        }
+    }
+
+    pub unsafe fn init<C: WorkKernelAPI>(&self, handler: fn()) {
+        *self.handler.get() = Some(handler);
+        C::k_work_init(self.work(), Self::run)
+    }
+
+    fn work(&self) -> *mut KWork {
+        self.work.get() as *mut KWork
+    }
+
+    unsafe extern "C" fn run(work: *mut k_work) {
+        let work = &*(work as *const Self);
+        if let Some(handler) = *work.handler.get() {
+            handler()
+        }
+    }
+
+    /// Queue the item on the system work queue. Has no effect if it is already queued.
+    pub fn submit<C: WorkKernelAPI>(&self) -> Result<Submitted, Error> {
+        Submitted::from_rc(unsafe { C::k_work_submit(self.work()) })
+    }
+
+    /// Queue the item on `queue`. Has no effect if it is already queued.
+    pub fn submit_to_queue<C: WorkKernelAPI>(&self, queue: WorkQueue) -> Result<Submitted, Error> {
+        Submitted::from_rc(unsafe { C::k_work_submit_to_queue(queue.kobj(), self.work()) })
+    }
+
+    /// Remove the item from its queue. Returns false if it is still running.
+    pub fn cancel<C: WorkKernelAPI>(&self) -> bool {
+        unsafe { C::k_work_cancel(self.work()) == 0 }
+    }
+
+    /// Block until the item is no longer pending. Returns true if it had to wait. Must be called
+    /// from a thread, not an interrupt.
+    pub fn flush<C: WorkKernelAPI>(&self) -> bool {
+        unsafe { C::k_work_flush(self.work()) }
+    }
+
+    /// Whether the item is queued or running
+    pub fn is_pending<C: WorkKernelAPI>(&self) -> bool {
+        unsafe { C::k_work_busy_get(self.work()) != 0 }
+    }
+}
+
+/// Statically allocated delayable work item running a plain function, like
+/// K_WORK_DELAYABLE_DEFINE. Declare with `k_work_delayable_define!`.
+#[repr(C)]
+pub struct StaticDelayableWork {
+    dwork: UnsafeCell<MaybeUninit<KWorkDelayable>>,
+    handler: UnsafeCell<Option<fn()>>,
+}
+
+unsafe impl Sync for StaticDelayableWork {}
+
+impl StaticDelayableWork {
+    /// Unsafe because `init` must be called before use, and the item must be static because the
+    /// kernel keeps a pointer to it.
+    pub const unsafe fn uninit() -> Self {
+        StaticDelayableWork {
+            dwork: UnsafeCell::new(MaybeUninit::uninit()),
+            handler: UnsafeCell::new(None),
+        }
+    }
+
+    pub unsafe fn init<C: WorkKernelAPI>(&self, handler: fn()) {
+        *self.handler.get() = Some(handler);
+        C::k_work_init_delayable(self.dwork(), Self::run)
+    }
+
+    fn dwork(&self) -> *mut KWorkDelayable {
+        self.dwork.get() as *mut KWorkDelayable
+    }
+
+    unsafe extern "C" fn run(work: *mut k_work) {
+        let work = &*(work as *const Self);
+        if let Some(handler) = *work.handler.get() {
+            handler()
+        }
+    }
+
+    /// Queue the item to run as soon as possible
+    pub fn submit<C: WorkKernelAPI>(&self, queue: WorkQueue) -> Result<Submitted, Error> {
+        self.schedule::<C>(queue, crate::K_NO_WAIT)
+    }
+
+    /// Queue the item to run after `delay`. Has no effect if it is already pending.
+    pub fn schedule<C: WorkKernelAPI>(
+        &self,
+        queue: WorkQueue,
+        delay: Timeout,
+    ) -> Result<Submitted, Error> {
+        Submitted::from_rc(unsafe {
+            C::k_work_schedule_for_queue(queue.kobj(), self.dwork(), delay.0)
+        })
+    }
+
+    /// Queue the item to run after `delay`, replacing the delay if it is already scheduled
+    pub fn reschedule<C: WorkKernelAPI>(
+        &self,
+        queue: WorkQueue,
+        delay: Timeout,
+    ) -> Result<Submitted, Error> {
+        Submitted::from_rc(unsafe {
+            C::k_work_reschedule_for_queue(queue.kobj(), self.dwork(), delay.0)
+        })
+    }
+
+    /// Remove the item from its queue or stop its delay. Returns false if it is still running.
+    pub fn cancel<C: WorkKernelAPI>(&self) -> bool {
+        unsafe { C::k_work_cancel_delayable(self.dwork()) == 0 }
+    }
+
+    /// Block until the item is no longer pending. Returns true if it had to wait. Must be called
+    /// from a thread, not an interrupt.
+    pub fn flush<C: WorkKernelAPI>(&self) -> bool {
+        unsafe { C::k_work_flush_delayable(self.dwork()) }
+    }
+
+    /// Whether the item is scheduled, queued or running
+    pub fn is_pending<C: WorkKernelAPI>(&self) -> bool {
+        unsafe { C::k_work_delayable_busy_get(self.dwork()) != 0 }
+    }
+}
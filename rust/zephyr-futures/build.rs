@@ -0,0 +1,12 @@
+fn main() {
+    // Same kernel version cfgs as zephyr-core, for wrappers of APIs only on newer kernels
+    let kernel_version_str_trimmed = std::env::var("ZEPHYR_KERNEL_VERSION_NUM")
+        .expect("ZEPHYR_KERNEL_VERSION_NUM must be set")
+        .trim_start_matches("0x").to_owned();
+    let kernel_version = u32::from_str_radix(&kernel_version_str_trimmed, 16)
+        .expect("ZEPHYR_KERNEL_VERSION_NUM must be an integer");
+
+    if kernel_version >= 0x3_00_00 {
+        println!("cargo:rustc-cfg=zephyr300");
+    }
+}
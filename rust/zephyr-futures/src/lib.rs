@@ -7,17 +7,25 @@ use core::marker::PhantomData;
 use core::pin::Pin;
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::task::{Context, Poll, Waker};
+use std::io;
 use std::time::Instant;
 
 use futures::future::{Future, FutureExt, LocalFutureObj};
 use futures::stream::Stream;
 use futures::task::{ArcWake, LocalSpawn, SpawnError};
 use log::trace;
+use zephyr::adc::{Adc, AdcSequence, AdcSyscalls};
+use zephyr::gpio::{self, gpio_port_pins_t, Edge, GpioCallback, GpioInterrupt};
+use zephyr::{IntoIoError, NegErr};
 
+#[cfg(zephyr300)]
+use zephyr_core::event::{Event, EventSignal};
 use zephyr_core::mutex::*;
 use zephyr_core::poll::*;
+use zephyr_core::queue::Queue;
 use zephyr_core::semaphore::*;
 use zephyr_core::thread::{ThreadId, ThreadSyscalls};
+use zephyr_core::timer::{CallbackTimer, KTimer, Timer, TimerIsr};
 use zephyr_core::Timeout;
 
 pub mod delay;
@@ -326,3 +334,281 @@ impl Stream for SemaphoreStream {
         }
     }
 }
+
+/// Stream of items removed from a k_queue, k_fifo or k_lifo
+pub struct QueueStream<T: 'static>(Queue<'static, T>);
+
+impl<T> QueueStream<T> {
+    pub fn new(queue: Queue<'static, T>) -> Self {
+        QueueStream(queue)
+    }
+}
+
+impl<T> Stream for QueueStream<T> {
+    type Item = Box<T>;
+
+    fn poll_next(self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<Self::Item>> {
+        // The kernel checks the queue again when polling, so no item can be missed between here
+        // and registering.
+        if let Some(item) = self.0.try_get::<zephyr::context::Any>() {
+            Poll::Ready(Some(item))
+        } else {
+            REACTOR.with(|r| {
+                r.borrow_mut()
+                    .as_mut()
+                    .expect("polled queue outside of reactor context")
+                    .register(self.0.kobj(), context);
+            });
+            Poll::Pending
+        }
+    }
+}
+
+type TimerSignalFn = Box<dyn FnMut(&TimerIsr) + Send>;
+
+/// Stream of expirations of a periodic timer
+///
+/// The timer's expiry handler raises a poll signal, which the reactor waits on. Each item is the
+/// number of expirations since the previous item, so none are lost if the task falls behind.
+pub struct TimerStream {
+    timer: CallbackTimer<'static, TimerSignalFn, fn(&TimerIsr)>,
+    signal: &'static KPollSignal,
+}
+
+impl TimerStream {
+    /// Take over `timer` to raise `signal` on expiry. Kernel mode only.
+    ///
+    /// Unsafe for the same reasons as `CallbackTimer::new`. The signal must not be used for
+    /// anything else.
+    pub unsafe fn new(timer: &'static KTimer, signal: &'static KPollSignal) -> Self {
+        let expiry: TimerSignalFn =
+            Box::new(move |_: &TimerIsr| signal.raise::<zephyr::context::Kernel>(0));
+        TimerStream {
+            timer: CallbackTimer::new(timer, expiry),
+            signal,
+        }
+    }
+
+    /// The timer, to start and stop it
+    pub fn timer(&self) -> Timer<'static> {
+        *self.timer
+    }
+}
+
+impl Stream for TimerStream {
+    type Item = u32;
+
+    fn poll_next(self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<Self::Item>> {
+        use zephyr::context::Any as C;
+        // Reset before reading the status so an expiry after the read leaves the signal raised
+        if self.signal.check::<C>().is_some() {
+            self.signal.reset::<C>();
+            let count = self.timer.status_get::<C>();
+            if count > 0 {
+                return Poll::Ready(Some(count));
+            }
+        }
+        current_reactor_register(self.signal, context);
+        Poll::Pending
+    }
+}
+
+/// Future resolving to the matched bits once any (or, with `wait_all`, every) event in `mask` has
+/// been posted through the `EventSignal`
+///
+/// k_poll cannot wait on the event object itself, so only posts made through the `EventSignal`
+/// wake the future. Events posted directly to the `KEvent`, such as by C code, are not seen until
+/// the future is polled again for some other reason. Post through the `EventSignal` from every
+/// producer that should complete the future.
+#[cfg(zephyr300)]
+pub struct EventFuture {
+    events: EventSignal<'static>,
+    mask: u32,
+    wait_all: bool,
+}
+
+#[cfg(zephyr300)]
+impl EventFuture {
+    pub fn new(events: EventSignal<'static>, mask: u32, wait_all: bool) -> Self {
+        EventFuture {
+            events,
+            mask,
+            wait_all,
+        }
+    }
+}
+
+#[cfg(zephyr300)]
+impl Future for EventFuture {
+    type Output = u32;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        use zephyr::context::Any as C;
+        // Reset before checking so a post after the check leaves the signal raised and wakes the
+        // reactor.
+        self.events.signal().reset::<C>();
+        if let Some(matched) = self.events.event().check::<C>(self.mask, self.wait_all) {
+            Poll::Ready(matched)
+        } else {
+            current_reactor_register(self.events.signal(), context);
+            Poll::Pending
+        }
+    }
+}
+
+type GpioSignalFn = Box<dyn FnMut(gpio_port_pins_t) + Send>;
+
+/// Future resolving once a GPIO pin sees an edge. Created by `PinExt::wait_for_edge`.
+///
+/// On first poll, a GPIO callback raising the poll signal is added and the pin's interrupt is
+/// configured for the edge. Both are undone on drop, leaving the pin's interrupt disabled.
+///
+/// The future borrows the pin mutably because it takes over the pin's interrupt configuration.
+/// Don't rely on an interrupt configured for the pin before or during the wait, e.g. for a
+/// `GpioCallback` added through the port.
+pub struct EdgeFuture<'a> {
+    pin: &'a mut gpio::Pin,
+    edge: Edge,
+    signal: &'static KPollSignal,
+    callback: Option<GpioCallback<GpioSignalFn>>,
+}
+
+impl<'a> EdgeFuture<'a> {
+    fn start(&mut self) -> io::Result<()> {
+        use zephyr::context::Any as C;
+
+        self.signal.reset::<C>();
+        let signal = self.signal;
+        let handler: GpioSignalFn = Box::new(move |_| signal.raise::<zephyr::context::Kernel>(0));
+        self.callback = Some(
+            self.pin
+                .port()
+                .add_callback::<zephyr::context::Kernel, _>(self.pin.mask(), handler)?,
+        );
+        self.pin.interrupt_configure::<C>(self.edge.into())
+    }
+}
+
+impl<'a> Future for EdgeFuture<'a> {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.callback.is_none() {
+            if let Err(e) = this.start() {
+                return Poll::Ready(Err(e));
+            }
+        } else if this.signal.check::<zephyr::context::Any>().is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        current_reactor_register(this.signal, context);
+        Poll::Pending
+    }
+}
+
+impl<'a> Drop for EdgeFuture<'a> {
+    fn drop(&mut self) {
+        if self.callback.take().is_some() {
+            self.pin
+                .interrupt_configure::<zephyr::context::Any>(GpioInterrupt::DISABLE)
+                .ok();
+        }
+    }
+}
+
+/// Async extensions to `zephyr::gpio::Pin`
+pub trait PinExt {
+    /// Wait for `edge` on the pin, raising `signal` from the GPIO interrupt. Kernel mode only.
+    ///
+    /// The pin must already be configured as an input. The signal must not be used for anything
+    /// else while the future exists. The pin's interrupt is disabled once the future is dropped.
+    fn wait_for_edge(&mut self, edge: Edge, signal: &'static KPollSignal) -> EdgeFuture<'_>;
+}
+
+impl PinExt for gpio::Pin {
+    fn wait_for_edge(&mut self, edge: Edge, signal: &'static KPollSignal) -> EdgeFuture<'_> {
+        EdgeFuture {
+            pin: self,
+            edge,
+            signal,
+            callback: None,
+        }
+    }
+}
+
+/// Future resolving when an ADC read completes. Created by `AdcExt::read_async`.
+///
+/// Sampling starts on first poll. Dropping the future while sampling blocks the whole thread,
+/// including every other task on the executor, until the driver is done writing to the buffer.
+pub struct AdcReadFuture<'a, 'b, C: AdcSyscalls> {
+    adc: &'a Adc<C>,
+    sequence: &'a mut AdcSequence<'b>,
+    signal: &'static KPollSignal,
+    started: bool,
+}
+
+impl<'a, 'b, C: AdcSyscalls> Future for AdcReadFuture<'a, 'b, C> {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        use zephyr::context::Any;
+
+        let this = self.get_mut();
+        if !this.started {
+            this.signal.reset::<Any>();
+            unsafe { this.adc.start_read(this.sequence, this.signal) }?;
+            this.started = true;
+        } else if let Some(result) = this.signal.check::<Any>() {
+            this.started = false;
+            return Poll::Ready(result.neg_err().map(|_| ()).map_err(|e| e.into_io_error()));
+        }
+        current_reactor_register(this.signal, context);
+        Poll::Pending
+    }
+}
+
+impl<'a, 'b, C: AdcSyscalls> Drop for AdcReadFuture<'a, 'b, C> {
+    fn drop(&mut self) {
+        use zephyr::context::Any;
+
+        if self.started {
+            let mut events = [KPollEvent::new()];
+            events[0].init(self.signal, PollMode::NotifyOnly);
+            events[..].poll::<Any>().ok();
+        }
+    }
+}
+
+/// Async extensions to `zephyr::adc::Adc`
+pub trait AdcExt<C: AdcSyscalls> {
+    /// Sample the sequence, raising `signal` from the driver when done. Requires
+    /// CONFIG_ADC_ASYNC.
+    ///
+    /// The signal must not be used for anything else while the future exists.
+    ///
+    /// # Safety
+    ///
+    /// As for `Adc::start_read`. Once polled, the future must be dropped or run to completion,
+    /// not leaked with e.g. `mem::forget`, since that ends the borrow of the sequence while the
+    /// driver may still be writing to its buffer.
+    unsafe fn read_async<'a, 'b>(
+        &'a self,
+        sequence: &'a mut AdcSequence<'b>,
+        signal: &'static KPollSignal,
+    ) -> AdcReadFuture<'a, 'b, C>;
+}
+
+impl<C: AdcSyscalls> AdcExt<C> for Adc<C> {
+    unsafe fn read_async<'a, 'b>(
+        &'a self,
+        sequence: &'a mut AdcSequence<'b>,
+        signal: &'static KPollSignal,
+    ) -> AdcReadFuture<'a, 'b, C> {
+        AdcReadFuture {
+            adc: self,
+            sequence,
+            signal,
+            started: false,
+        }
+    }
+}
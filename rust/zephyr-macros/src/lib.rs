@@ -128,3 +128,902 @@ pub fn k_sem_define(item: TokenStream) -> TokenStream {
 
     expanded.into()
 }
+
+/// Parse `NAME, Type, depth`. The type may contain commas (e.g. generics), so it is everything
+/// between the first comma and the last.
+fn get_msgq_args(item: TokenStream) -> Option<(Ident, proc_macro2::TokenStream, TokenTree)> {
+    let item = proc_macro2::TokenStream::from(item);
+    let mut tokens: Vec<TokenTree> = item.into_iter().collect();
+
+    // Allow a trailing comma
+    if let Some(TokenTree::Punct(p)) = tokens.last() {
+        if p.as_char() == ',' {
+            tokens.pop();
+        }
+    }
+    if tokens.len() < 5 {
+        return None;
+    }
+    let depth = tokens.pop().unwrap();
+    match tokens.pop() {
+        Some(TokenTree::Punct(ref p)) if p.as_char() == ',' => (),
+        _ => return None,
+    }
+    let mut iter = tokens.into_iter();
+    if let (Some(TokenTree::Ident(ident)), Some(TokenTree::Punct(p))) = (iter.next(), iter.next()) {
+        if p.as_char() == ',' {
+            return Some((ident, iter.collect(), depth));
+        }
+    }
+    None
+}
+
+#[proc_macro]
+pub fn k_msgq_define(item: TokenStream) -> TokenStream {
+    let (ident, ty, depth) =
+        get_msgq_args(item).expect("Expected 3 comma-separated arguments: name, type, depth");
+
+    let section = Literal::string(&format!("._k_msgq.static.{}", ident));
+    // Not in the Rust data partition. Only the kernel accesses the ring buffer.
+    let buf_section = Literal::string(&format!(".noinit.rust_msgq_buf.{}", ident));
+    let kobj = Ident::new(&format!("_rust_msgq_{}", ident), ident.span());
+    let buf = Ident::new(&format!("_rust_msgq_buf_{}", ident), ident.span());
+    let ctor = Ident::new(&format!("_rust_msgq_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_msgq_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The static storage for the object, itself
+        #[link_section = #section]
+        #[allow(non_upper_case_globals)]
+        static #kobj: zephyr::msgq::global::k_msgq = unsafe { zephyr::msgq::global::k_msgq::uninit() };
+
+        // Ring buffer, aligned for the message type
+        #[link_section = #buf_section]
+        #[allow(non_upper_case_globals)]
+        static mut #buf: [core::mem::MaybeUninit<#ty>; #depth] = [core::mem::MaybeUninit::uninit(); #depth];
+
+        // Typed access to the queue
+        static #ident: zephyr::msgq::MsgQueue<'static, #ty> = unsafe { zephyr::msgq::MsgQueue::from_ptr(#kobj.as_ptr()) };
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::msgq::Msgq;
+            unsafe {
+                #kobj.kobj().init(
+                    core::ptr::addr_of_mut!(#buf) as *mut u8,
+                    core::mem::size_of::<#ty>(),
+                    #depth as u32,
+                )
+            }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+/// Declare a `Slab<'static, Type>` of `num_blocks` blocks, like K_MEM_SLAB_DEFINE
+///
+/// `k_mem_slab_define!(NAME, Type, num_blocks)`
+///
+/// The slab and its blocks are kernel memory, outside of the Rust partition, so the slab is only
+/// usable from kernel mode. User threads could otherwise corrupt its wait queue and free list.
+#[proc_macro]
+pub fn k_mem_slab_define(item: TokenStream) -> TokenStream {
+    let (ident, ty, num_blocks) = get_msgq_args(item)
+        .expect("Expected 3 comma-separated arguments: name, type, number of blocks");
+
+    let section = Literal::string(&format!(".noinit.rust_mem_slab.{}", ident));
+    let buf_section = Literal::string(&format!(".noinit.rust_mem_slab_buf.{}", ident));
+
+    let kobj = Ident::new(&format!("_rust_mem_slab_{}", ident), ident.span());
+    let buf = Ident::new(&format!("_rust_mem_slab_buf_{}", ident), ident.span());
+    let ctor = Ident::new(&format!("_rust_mem_slab_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_mem_slab_init_{}", ident), ident.span());
+    // Separate to stay under quote's recursion limit
+    let init = quote! {
+        core::ptr::write_bytes(#kobj.as_ptr() as *mut zephyr::slab::KMemSlab, 0, 1);
+        #kobj.kobj().init::<zephyr::context::Kernel>(
+            core::ptr::addr_of_mut!(#buf) as *mut u8,
+            zephyr::slab::Slab::<#ty>::BLOCK_SIZE,
+            #num_blocks as u32,
+        )
+        .expect("k_mem_slab_init")
+    };
+    let expanded = quote! {
+        // The static storage for the object, itself. Not in the k_mem_slab iterable section,
+        // which the kernel would initialize before the constructor runs.
+        #[link_section = #section]
+        #[allow(non_upper_case_globals)]
+        static #kobj: zephyr::slab::global::k_mem_slab = unsafe { zephyr::slab::global::k_mem_slab::uninit() };
+
+        // The blocks
+        #[link_section = #buf_section]
+        #[allow(non_upper_case_globals)]
+        static mut #buf: core::mem::MaybeUninit<[zephyr::slab::SlabBlock<#ty>; #num_blocks]> = core::mem::MaybeUninit::uninit();
+
+        // Typed access to the slab
+        static #ident: zephyr::slab::Slab<'static, #ty> = unsafe { zephyr::slab::Slab::from_ptr(#kobj.as_ptr()) };
+
+        // A constructor function that zeroes the noinit object like .bss and calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::slab::MemSlab;
+            unsafe { #init }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+#[proc_macro]
+pub fn k_queue_define(item: TokenStream) -> TokenStream {
+    let ident = get_single_arg(item);
+    let section = Literal::string(&format!("._k_queue.static.{}", ident));
+    let ctor = Ident::new(&format!("_rust_queue_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_queue_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The static storage for the object, itself
+        #[link_section = #section]
+        static #ident: zephyr::queue::global::k_queue = unsafe { zephyr::queue::global::k_queue::uninit() };
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::queue::QueueSyscalls;
+            unsafe { zephyr::context::Kernel::k_queue_init(#ident.kobj()) }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+fn get_pipe_args(item: TokenStream) -> Option<(Ident, Literal)> {
+    let item = proc_macro2::TokenStream::from(item);
+    let mut iter = item.into_iter();
+
+    if let (
+        Some(TokenTree::Ident(ident)),
+        Some(TokenTree::Punct(p1)),
+        Some(TokenTree::Literal(l1)),
+    ) = (iter.next(), iter.next(), iter.next())
+    {
+        if p1.as_char() == ',' {
+            return Some((ident, l1));
+        }
+    }
+    None
+}
+
+#[proc_macro]
+pub fn k_pipe_define(item: TokenStream) -> TokenStream {
+    let (ident, size) =
+        get_pipe_args(item).expect("Expected 2 comma-separated arguments: name, buffer size");
+
+    let section = Literal::string(&format!("._k_pipe.static.{}", ident));
+    // Not in the Rust data partition. Only the kernel accesses the ring buffer.
+    let buf_section = Literal::string(&format!(".noinit.rust_pipe_buf.{}", ident));
+    let buf = Ident::new(&format!("_rust_pipe_buf_{}", ident), ident.span());
+    let ctor = Ident::new(&format!("_rust_pipe_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_pipe_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The static storage for the object, itself
+        #[link_section = #section]
+        static #ident: zephyr::pipe::global::k_pipe = unsafe { zephyr::pipe::global::k_pipe::uninit() };
+
+        // Ring buffer. Zero size makes an unbuffered pipe.
+        #[link_section = #buf_section]
+        #[allow(non_upper_case_globals)]
+        static mut #buf: [core::mem::MaybeUninit<u8>; #size] = [core::mem::MaybeUninit::uninit(); #size];
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::pipe::RawPipe;
+            unsafe {
+                #ident.kobj().init(core::ptr::addr_of_mut!(#buf) as *mut u8, #size)
+            }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+#[proc_macro]
+pub fn k_condvar_define(item: TokenStream) -> TokenStream {
+    let ident = get_single_arg(item);
+    let section = Literal::string(&format!("._k_condvar.static.{}", ident));
+    let ctor = Ident::new(&format!("_rust_condvar_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_condvar_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The static storage for the object, itself
+        #[link_section = #section]
+        static #ident: zephyr::condvar::global::k_condvar = unsafe { zephyr::condvar::global::k_condvar::uninit() };
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::condvar::CondvarSyscalls;
+            unsafe { zephyr::context::Kernel::k_condvar_init(#ident.kobj()); }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+#[proc_macro]
+pub fn k_event_define(item: TokenStream) -> TokenStream {
+    let ident = get_single_arg(item);
+    let section = Literal::string(&format!("._k_event.static.{}", ident));
+    let ctor = Ident::new(&format!("_rust_event_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_event_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The static storage for the object, itself
+        #[link_section = #section]
+        static #ident: zephyr::event::global::k_event = unsafe { zephyr::event::global::k_event::uninit() };
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::event::*;
+            unsafe { #ident.init::<zephyr::context::Kernel>() }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+#[proc_macro]
+pub fn k_timer_define(item: TokenStream) -> TokenStream {
+    let ident = get_single_arg(item);
+    let section = Literal::string(&format!("._k_timer.static.{}", ident));
+    let ctor = Ident::new(&format!("_rust_timer_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_timer_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The static storage for the object, itself
+        #[link_section = #section]
+        static #ident: zephyr::timer::global::k_timer = unsafe { zephyr::timer::global::k_timer::uninit() };
+
+        // A constructor function that calls its init. Handlers can be attached later with
+        // CallbackTimer.
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::timer::RawTimer;
+            unsafe { #ident.kobj().init(None, None) }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+/// Parse `NAME, handler`. The handler may be a path.
+fn get_work_args(item: TokenStream) -> Option<(Ident, proc_macro2::TokenStream)> {
+    let item = proc_macro2::TokenStream::from(item);
+    let mut iter = item.into_iter();
+
+    if let (Some(TokenTree::Ident(ident)), Some(TokenTree::Punct(p))) = (iter.next(), iter.next()) {
+        if p.as_char() == ',' {
+            return Some((ident, iter.collect()));
+        }
+    }
+    None
+}
+
+/// Declare a `StaticWork` running `handler`, like K_WORK_DEFINE
+///
+/// `k_work_define!(NAME, handler)`
+#[proc_macro]
+pub fn k_work_define(item: TokenStream) -> TokenStream {
+    work_define(item, quote!(zephyr::work::StaticWork))
+}
+
+/// Declare a `StaticDelayableWork` running `handler`, like K_WORK_DELAYABLE_DEFINE
+///
+/// `k_work_delayable_define!(NAME, handler)`
+#[proc_macro]
+pub fn k_work_delayable_define(item: TokenStream) -> TokenStream {
+    work_define(item, quote!(zephyr::work::StaticDelayableWork))
+}
+
+fn work_define(item: TokenStream, ty: proc_macro2::TokenStream) -> TokenStream {
+    let (ident, handler) =
+        get_work_args(item).expect("Expected 2 comma-separated arguments: name, handler");
+
+    let ctor = Ident::new(&format!("_rust_work_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_work_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The static storage for the work item. Not a kernel object, so no special section.
+        static #ident: #ty = unsafe { #ty::uninit() };
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            unsafe { #ident.init::<zephyr::context::Kernel>(#handler) }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+/// Split the macro input at top level commas, dropping a trailing one
+fn split_args(item: TokenStream) -> Vec<Vec<TokenTree>> {
+    let item = proc_macro2::TokenStream::from(item);
+    let mut args = vec![Vec::new()];
+    for tt in item {
+        match tt {
+            TokenTree::Punct(ref p) if p.as_char() == ',' => args.push(Vec::new()),
+            tt => args.last_mut().unwrap().push(tt),
+        }
+    }
+    if args.last().map_or(false, |arg| arg.is_empty()) {
+        args.pop();
+    }
+    args
+}
+
+/// Parse an integer literal, possibly negated
+fn parse_int(tokens: &[TokenTree]) -> Option<i64> {
+    let (negative, lit) = match tokens {
+        [TokenTree::Punct(p), TokenTree::Literal(l)] if p.as_char() == '-' => (true, l),
+        [TokenTree::Literal(l)] => (false, l),
+        _ => return None,
+    };
+    let lit = lit.to_string();
+    let digits: String = lit
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .chars()
+        .filter(|&c| c != '_')
+        .collect();
+    let value: i64 = digits.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Declare a thread created by the kernel at boot, like K_THREAD_DEFINE
+///
+/// `k_thread_define!(NAME, entry, stack = 2048, priority = 5, delay = 0, user, fp_regs, essential,
+/// inherit_perms)`
+///
+/// `entry` is a `fn()`. Everything after it is optional. The priority defaults to the lowest
+/// application priority, the stack to 1024 bytes and the delay to 0 ms. A delay of -1 creates the
+/// thread without starting it. `NAME` is the `ThreadId` of the new thread.
+///
+/// A `user` thread is created in kernel mode, joins the Rust memory domain and then enters user
+/// mode to run `entry`. Its stack is placed with the user stacks so the kernel registers it as a
+/// stack object. Without CONFIG_USERSPACE, `user` threads run in kernel mode.
+#[proc_macro]
+pub fn k_thread_define(item: TokenStream) -> TokenStream {
+    let mut args = split_args(item).into_iter();
+    let ident = match args.next().as_deref() {
+        Some([TokenTree::Ident(ident)]) => ident.clone(),
+        arg => panic!("k_thread_define expects a name first. Got {:?}", arg),
+    };
+    let entry: proc_macro2::TokenStream = args
+        .next()
+        .expect("k_thread_define expects an entry function")
+        .into_iter()
+        .collect();
+
+    let mut stack_size = 1024usize;
+    let mut priority = quote!(zephyr::thread::LOWEST_APPLICATION_PRIORITY);
+    let mut delay = 0i64;
+    let mut options = Vec::new();
+    let mut user = false;
+    for arg in args {
+        let key = match arg.first() {
+            Some(TokenTree::Ident(key)) => key.to_string(),
+            _ => panic!("Expected an option. Got {:?}", arg),
+        };
+        let value = match arg.get(1) {
+            Some(TokenTree::Punct(p)) if p.as_char() == '=' => {
+                Some(parse_int(&arg[2..]).expect("Expected an integer literal"))
+            }
+            None => None,
+            _ => panic!("Expected `{} = <value>`", key),
+        };
+        match (key.as_str(), value) {
+            ("stack", Some(v)) if v > 0 => stack_size = v as usize,
+            ("priority", Some(_)) => priority = arg[2..].iter().cloned().collect(),
+            ("delay", Some(v)) if v >= -1 => delay = v,
+            ("user", None) => user = true,
+            ("fp_regs", None) => options.push(quote!(FP_REGS)),
+            ("essential", None) => options.push(quote!(ESSENTIAL)),
+            ("inherit_perms", None) => options.push(quote!(INHERIT_PERMS)),
+            _ => panic!("Unknown thread option {:?}", arg),
+        }
+    }
+
+    // Same layout as K_THREAD_STACK_DEFINE, with the arch parameters passed from CMake
+    let user_stack = user && userspace_enabled();
+    let mut align = env_usize("RUST_THREAD_STACK_ALIGN", 8);
+    let reserved = env_usize("RUST_THREAD_STACK_RESERVED", 0);
+    let mut stack_len = (stack_size + reserved + align - 1) / align * align;
+    if user_stack
+        && std::env::var("CONFIG_MPU_REQUIRES_POWER_OF_TWO_ALIGNMENT").map_or(false, |v| v == "y")
+    {
+        // The MPU region covering a user stack must be a power of two aligned to its size
+        stack_len = stack_len.next_power_of_two().max(32);
+        align = stack_len;
+    }
+
+    let stack_size = Literal::usize_unsuffixed(stack_size);
+    let stack_len = Literal::usize_unsuffixed(stack_len);
+    let align = Literal::usize_unsuffixed(align);
+    let delay = if delay < 0 {
+        quote!(-1)
+    } else {
+        let delay = Literal::i32_unsuffixed(delay as i32);
+        quote!(#delay)
+    };
+    let name = Literal::string(&format!("{}\0", ident));
+    let section = Literal::string(&format!("._static_thread_data.static.{}", ident));
+    let obj_section = Literal::string(&format!(".noinit.rust_thread.{}", ident));
+    let stack_section = if user_stack {
+        Literal::string(&format!(".user_stacks.rust_thread_stack.{}", ident))
+    } else {
+        Literal::string(&format!(".noinit.rust_thread_stack.{}", ident))
+    };
+    let call = if user {
+        quote!(zephyr::thread::user_thread_main(entry))
+    } else {
+        quote!(entry())
+    };
+    let obj = Ident::new(&format!("_rust_thread_obj_{}", ident), ident.span());
+    let stack_ty = Ident::new(&format!("_RustThreadStack_{}", ident), ident.span());
+    let stack = Ident::new(&format!("_rust_thread_stack_{}", ident), ident.span());
+    let data = Ident::new(&format!("_rust_thread_data_{}", ident), ident.span());
+    let trampoline = Ident::new(&format!("_rust_thread_entry_{}", ident), ident.span());
+    let ctor = Ident::new(&format!("_rust_thread_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_thread_init_{}", ident), ident.span());
+    let expanded = quote! {
+        // The thread object. Not in the Rust data partition, so user threads cannot touch it.
+        #[link_section = #obj_section]
+        #[allow(non_upper_case_globals)]
+        static #obj: zephyr::thread::global::k_thread = unsafe { zephyr::thread::global::k_thread::uninit() };
+
+        #[repr(C, align(#align))]
+        #[allow(non_camel_case_types)]
+        struct #stack_ty([zephyr::thread::KThreadStack; #stack_len]);
+
+        // Never loaded. An array of stack elements so the kernel finds it as a stack object.
+        #[link_section = #stack_section]
+        #[allow(non_upper_case_globals)]
+        static #stack: zephyr::thread::StaticThreadStack<#stack_ty> = zephyr::thread::StaticThreadStack::new(
+            #stack_ty([zephyr::thread::KThreadStack { data: 0 }; #stack_len]),
+        );
+
+        #[allow(non_snake_case)]
+        unsafe extern "C" fn #trampoline(
+            _p1: *mut core::ffi::c_void,
+            _p2: *mut core::ffi::c_void,
+            _p3: *mut core::ffi::c_void,
+        ) {
+            let entry: fn() = #entry;
+            #call
+        }
+
+        // Found by the kernel at boot
+        #[used]
+        #[link_section = #section]
+        #[allow(non_upper_case_globals)]
+        static #data: zephyr::thread::StaticThreadData = unsafe {
+            zephyr::thread::StaticThreadData::new(
+                #obj.as_ptr(),
+                #stack.as_ptr(),
+                #stack_size,
+                #trampoline,
+                #priority,
+                zephyr::thread::ThreadOptions(0 #(| zephyr::thread::ThreadOptions::#options.0)*),
+                #delay,
+                #name.as_ptr() as *const _,
+            )
+        };
+
+        static #ident: zephyr::thread::ThreadId = unsafe { zephyr::thread::ThreadId::from_ptr(#obj.as_ptr()) };
+
+        // The object is in noinit. Zero it like .bss before the kernel creates the thread.
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            unsafe { core::ptr::write_bytes(#obj.as_ptr() as *mut zephyr::thread::KThread, 0, 1) }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+/// Parse `NAME: Type = init`. The type ends at the first `=` outside of angle brackets.
+fn get_static_mutex_args(
+    item: TokenStream,
+) -> Option<(Ident, proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let item = proc_macro2::TokenStream::from(item);
+    let mut tokens: Vec<TokenTree> = item.into_iter().collect();
+
+    // Allow a trailing semicolon
+    if let Some(TokenTree::Punct(p)) = tokens.last() {
+        if p.as_char() == ';' {
+            tokens.pop();
+        }
+    }
+    let mut iter = tokens.into_iter();
+    let ident = match (iter.next(), iter.next()) {
+        (Some(TokenTree::Ident(ident)), Some(TokenTree::Punct(ref p))) if p.as_char() == ':' => {
+            ident
+        }
+        _ => return None,
+    };
+
+    let mut ty = Vec::new();
+    let mut depth = 0usize;
+    let mut prev_dash = false;
+    loop {
+        let tt = iter.next()?;
+        if let TokenTree::Punct(ref p) = tt {
+            match p.as_char() {
+                '=' if depth == 0 => break,
+                '<' => depth += 1,
+                // Not the arrow of a function type
+                '>' if !prev_dash => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+            prev_dash = p.as_char() == '-';
+        } else {
+            prev_dash = false;
+        }
+        ty.push(tt);
+    }
+    let init: proc_macro2::TokenStream = iter.collect();
+    if ty.is_empty() || init.is_empty() {
+        return None;
+    }
+    Some((ident, ty.into_iter().collect(), init))
+}
+
+/// Declare a `Mutex<'static, Type>` together with its kernel object
+///
+/// `static_mutex!(NAME: Type = init);`
+///
+/// The kernel object is placed with the other static mutexes in kernel memory. The data is an
+/// ordinary Rust static, so is in the Rust memory partition where user threads can reach it.
+#[proc_macro]
+pub fn static_mutex(item: TokenStream) -> TokenStream {
+    let (ident, ty, init) = get_static_mutex_args(item).expect("Expected `NAME: Type = init`");
+
+    let section = Literal::string(&format!("._k_mutex.static.{}", ident));
+    let kobj = Ident::new(&format!("_rust_static_mutex_{}", ident), ident.span());
+    let ctor = Ident::new(&format!("_rust_static_mutex_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(
+        &format!("_ctor_rust_static_mutex_init_{}", ident),
+        ident.span(),
+    );
+    let expanded = quote! {
+        // The static storage for the kernel object. Only reachable through the Mutex below.
+        #[link_section = #section]
+        #[allow(non_upper_case_globals)]
+        static #kobj: zephyr::mutex::global::k_mutex = unsafe { zephyr::mutex::global::k_mutex::uninit() };
+
+        static #ident: zephyr::mutex::Mutex<'static, #ty> = unsafe { zephyr::mutex::Mutex::new(&*#kobj.as_ptr(), #init) };
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            use zephyr::mutex::RawMutex;
+            unsafe { #kobj.init::<zephyr::context::Kernel>() }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+/// Declare a `KHeap` of `size` bytes, usable as an `Allocator`
+///
+/// `k_heap_define!(NAME, 4096)` or `k_heap_define!(NAME, 4096, partition = my_partition)`
+///
+/// With a partition, the heap is placed in that app memory partition like K_APP_DMEM, and its
+/// memory in the partition's bss like K_APP_BMEM, so only threads in a domain with the partition
+/// can allocate from it. The partition
+/// must be defined with K_APPMEM_PARTITION_DEFINE. Otherwise the heap is an ordinary Rust static
+/// in the Rust memory partition.
+#[proc_macro]
+pub fn k_heap_define(item: TokenStream) -> TokenStream {
+    let mut args = split_args(item).into_iter();
+    let ident = match args.next().as_deref() {
+        Some([TokenTree::Ident(ident)]) => ident.clone(),
+        arg => panic!("k_heap_define expects a name first. Got {:?}", arg),
+    };
+    let size: proc_macro2::TokenStream = args
+        .next()
+        .expect("k_heap_define expects a size in bytes")
+        .into_iter()
+        .collect();
+
+    let mut partition = None;
+    for arg in args {
+        match arg.as_slice() {
+            [TokenTree::Ident(key), TokenTree::Punct(p), TokenTree::Ident(part)]
+                if key == "partition" && p.as_char() == '=' =>
+            {
+                partition = Some(part.clone())
+            }
+            _ => panic!("Unknown heap option {:?}", arg),
+        }
+    }
+
+    let userspace = std::env::var("CONFIG_USERSPACE").map_or(false, |v| v == "y");
+    let (link_section, buf_link_section) = match partition {
+        // Same sections as K_APP_DMEM and K_APP_BMEM. Without userspace, those are just .data and
+        // .bss.
+        Some(part) if userspace => {
+            let section = Literal::string(&format!("data_smem_{}_data", part));
+            let buf_section = Literal::string(&format!("data_smem_{}_bss", part));
+            (
+                quote!(#[link_section = #section]),
+                quote!(#[link_section = #buf_section]),
+            )
+        }
+        _ => (quote!(), quote!()),
+    };
+    let buf_ty = Ident::new(&format!("_RustHeapBuf_{}", ident), ident.span());
+    let buf = Ident::new(&format!("_rust_heap_buf_{}", ident), ident.span());
+    let ctor = Ident::new(&format!("_rust_heap_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(&format!("_ctor_rust_heap_init_{}", ident), ident.span());
+    let expanded = quote! {
+        #link_section
+        static #ident: zephyr::mempool::KHeap = unsafe { zephyr::mempool::KHeap::uninit() };
+
+        #[repr(C, align(8))]
+        #[allow(non_camel_case_types)]
+        struct #buf_ty([core::mem::MaybeUninit<u8>; #size]);
+
+        // The heap's memory. User threads allocating from the heap also access this directly.
+        #buf_link_section
+        #[allow(non_upper_case_globals)]
+        static mut #buf: #buf_ty = #buf_ty([core::mem::MaybeUninit::uninit(); #size]);
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            unsafe { #ident.init(core::ptr::addr_of_mut!(#buf) as *mut u8, #size) }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+fn userspace_enabled() -> bool {
+    std::env::var("CONFIG_USERSPACE").map_or(false, |v| v == "y")
+}
+
+/// Declare an app memory partition, like K_APPMEM_PARTITION_DEFINE
+///
+/// `k_appmem_partition_define!(NAME)` declares `NAME: zephyr::memdomain::MemPartition`. Place
+/// statics in it with `#[app_mem(NAME)]` and grant threads access by adding it to a domain. The
+/// partition's bss is zeroed at boot along with the partitions defined in C.
+#[proc_macro]
+pub fn k_appmem_partition_define(item: TokenStream) -> TokenStream {
+    let ident = get_single_arg(item);
+    if !userspace_enabled() {
+        return quote! {
+            #[allow(non_upper_case_globals)]
+            static #ident: zephyr::memdomain::MemPartition = unsafe { zephyr::memdomain::MemPartition::uninit() };
+        }
+        .into();
+    }
+
+    let symbol = |suffix: &str| {
+        (
+            Ident::new(&format!("_rust_part_{}_{}", ident, suffix), ident.span()),
+            Literal::string(&format!("z_data_smem_{}_{}", ident, suffix)),
+        )
+    };
+    let (start, start_name) = symbol("part_start");
+    let (size, size_name) = symbol("part_size");
+    let (bss_start, bss_start_name) = symbol("bss_start");
+    let (bss_size, bss_size_name) = symbol("bss_size");
+    let section = Literal::string(&format!(".noinit.rust_mem_partition.{}", ident));
+    let region_section = Literal::string(&format!(".app_regions.{}", ident));
+    let region = Ident::new(&format!("_rust_app_region_{}", ident), ident.span());
+    let ctor = Ident::new(&format!("_rust_mem_partition_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(
+        &format!("_ctor_rust_mem_partition_init_{}", ident),
+        ident.span(),
+    );
+    let expanded = quote! {
+        // Bounds of the partition, from the linker script's app_smem section
+        #[allow(non_upper_case_globals)]
+        extern "C" {
+            #[link_name = #start_name]
+            static #start: u8;
+            #[link_name = #size_name]
+            static #size: u8;
+            #[link_name = #bss_start_name]
+            static #bss_start: u8;
+            #[link_name = #bss_size_name]
+            static #bss_size: u8;
+        }
+
+        // The partition struct is read by the kernel, so keep it in kernel memory
+        #[link_section = #section]
+        #[allow(non_upper_case_globals)]
+        static #ident: zephyr::memdomain::MemPartition = unsafe { zephyr::memdomain::MemPartition::uninit() };
+
+        // Picked up by gen_app_partitions.py, which lays out the partition and zeroes its bss
+        #[used]
+        #[link_section = #region_section]
+        #[allow(non_upper_case_globals)]
+        static #region: zephyr::memdomain::AppRegion = unsafe {
+            zephyr::memdomain::AppRegion::new(
+                core::ptr::addr_of!(#bss_start),
+                core::ptr::addr_of!(#bss_size),
+            )
+        };
+
+        // A constructor function that calls its init
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            unsafe {
+                #ident.init(
+                    core::ptr::addr_of!(#start),
+                    core::ptr::addr_of!(#size) as usize,
+                )
+            }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
+
+/// Place a static in an app memory partition, like K_APP_DMEM
+///
+/// `#[app_mem(NAME)] static mut X: u32 = 0;` where `NAME` is a partition from
+/// `k_appmem_partition_define!` or K_APPMEM_PARTITION_DEFINE. Without userspace this does nothing.
+#[proc_macro_attribute]
+pub fn app_mem(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let part = get_single_arg(attr);
+    let item = proc_macro2::TokenStream::from(item);
+    if !userspace_enabled() {
+        return item.into();
+    }
+    let section = Literal::string(&format!("data_smem_{}_data", part));
+    let expanded = quote! {
+        #[link_section = #section]
+        #item
+    };
+
+    expanded.into()
+}
+
+/// Place a zero-initialized static in an app memory partition's bss, like K_APP_BMEM
+///
+/// `#[app_bmem(NAME)] static mut X: [u8; 64] = [0; 64];` The initializer must be all zeros. It
+/// is not stored in the image, and the partition's bss is zeroed at boot instead. Without
+/// userspace this does nothing.
+#[proc_macro_attribute]
+pub fn app_bmem(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let part = get_single_arg(attr);
+    let item = proc_macro2::TokenStream::from(item);
+    if !userspace_enabled() {
+        return item.into();
+    }
+    let section = Literal::string(&format!("data_smem_{}_bss", part));
+    let expanded = quote! {
+        #[link_section = #section]
+        #item
+    };
+
+    expanded.into()
+}
+
+/// Declare a `zephyr::memdomain::StaticMemDomain`, initialized at runtime with its partitions
+///
+/// `k_mem_domain_define!(NAME)`, then `NAME.init::<Kernel>(&[&PART])` from kernel mode.
+#[proc_macro]
+pub fn k_mem_domain_define(item: TokenStream) -> TokenStream {
+    let ident = get_single_arg(item);
+    let section = Literal::string(&format!(".noinit.rust_mem_domain.{}", ident));
+    let ctor = Ident::new(&format!("_rust_mem_domain_init_{}", ident), ident.span());
+    let ctor_ptr = Ident::new(
+        &format!("_ctor_rust_mem_domain_init_{}", ident),
+        ident.span(),
+    );
+    // Domains are read by the kernel, so keep them out of the Rust partition
+    let link_section = if userspace_enabled() {
+        quote!(#[link_section = #section])
+    } else {
+        quote!()
+    };
+    let expanded = quote! {
+        #link_section
+        static #ident: zephyr::memdomain::StaticMemDomain = unsafe { zephyr::memdomain::StaticMemDomain::uninit() };
+
+        // A constructor function that zeroes the noinit storage
+        #[allow(non_snake_case)]
+        extern "C" fn #ctor() {
+            unsafe { #ident.zero() }
+        }
+
+        // Add a pointer to the constructor to .ctors table
+        #[used]
+        #[link_section = ".ctors"]
+        #[allow(non_upper_case_globals)]
+        static #ctor_ptr: extern "C" fn() = #ctor;
+    };
+
+    expanded.into()
+}
@@ -9,6 +9,10 @@
 #include <drivers/uart.h>
 #include <uart_buffered.h>
 #include <drivers/eeprom.h>
+#include <drivers/gpio.h>
+#include <drivers/i2c.h>
+#include <drivers/spi.h>
+#include <drivers/adc.h>
 
 #ifdef CONFIG_POSIX_CLOCK
 #include <posix/time.h>
@@ -0,0 +1,416 @@
+use std::io;
+use std::marker::PhantomData;
+use std::ptr;
+
+pub use zephyr_sys::raw::{adc_channel_cfg, adc_gain, adc_reference, adc_sequence};
+
+use super::NegErrno;
+use crate::device::Device;
+use crate::poll_signal::KPollSignal;
+
+// Acquisition time units from drivers/adc.h. Defined as macros, so not in the bindings.
+pub const ADC_ACQ_TIME_TICKS: u16 = 0;
+pub const ADC_ACQ_TIME_MICROSECONDS: u16 = 1;
+pub const ADC_ACQ_TIME_NANOSECONDS: u16 = 2;
+pub const ADC_ACQ_TIME_DEFAULT: u16 = 0;
+
+/// Raw syscall API
+pub trait AdcSyscalls {
+    unsafe fn adc_channel_setup(
+        device: *mut Device,
+        channel_cfg: &adc_channel_cfg,
+    ) -> io::Result<()>;
+    unsafe fn adc_read(device: *mut Device, sequence: &adc_sequence) -> io::Result<()>;
+    /// Requires CONFIG_ADC_ASYNC
+    unsafe fn adc_read_async(
+        device: *mut Device,
+        sequence: &adc_sequence,
+        signal: &KPollSignal,
+    ) -> io::Result<()>;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl AdcSyscalls for $context_struct {
+            #[inline(always)]
+            unsafe fn adc_channel_setup(
+                device: *mut Device,
+                channel_cfg: &adc_channel_cfg,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::adc_channel_setup(device, channel_cfg)
+                    .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn adc_read(device: *mut Device, sequence: &adc_sequence) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::adc_read(device, sequence).zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn adc_read_async(
+                device: *mut Device,
+                sequence: &adc_sequence,
+                signal: &KPollSignal,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::adc_read_async(
+                    device,
+                    sequence,
+                    signal as *const _ as *mut _,
+                )
+                .zero_or_neg_errno()
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+extern "C" {
+    // From rust-adc.c
+    fn rust_adc_channel_cfg_init(
+        cfg: *mut adc_channel_cfg,
+        gain: adc_gain,
+        reference: adc_reference,
+        acquisition_time: u16,
+        channel_id: u8,
+        differential: bool,
+        input_positive: u8,
+        input_negative: u8,
+    );
+    fn rust_adc_ref_internal(device: *const Device) -> u16;
+}
+
+/// Gain applied to the input before conversion
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdcGain {
+    Gain1_6,
+    Gain1_5,
+    Gain1_4,
+    Gain1_3,
+    Gain1_2,
+    Gain2_3,
+    Gain1,
+    Gain2,
+    Gain3,
+    Gain4,
+    Gain8,
+    Gain16,
+    Gain32,
+    Gain64,
+    Gain128,
+}
+
+impl AdcGain {
+    pub fn raw(self) -> adc_gain {
+        use zephyr_sys::raw::*;
+        match self {
+            AdcGain::Gain1_6 => adc_gain_ADC_GAIN_1_6,
+            AdcGain::Gain1_5 => adc_gain_ADC_GAIN_1_5,
+            AdcGain::Gain1_4 => adc_gain_ADC_GAIN_1_4,
+            AdcGain::Gain1_3 => adc_gain_ADC_GAIN_1_3,
+            AdcGain::Gain1_2 => adc_gain_ADC_GAIN_1_2,
+            AdcGain::Gain2_3 => adc_gain_ADC_GAIN_2_3,
+            AdcGain::Gain1 => adc_gain_ADC_GAIN_1,
+            AdcGain::Gain2 => adc_gain_ADC_GAIN_2,
+            AdcGain::Gain3 => adc_gain_ADC_GAIN_3,
+            AdcGain::Gain4 => adc_gain_ADC_GAIN_4,
+            AdcGain::Gain8 => adc_gain_ADC_GAIN_8,
+            AdcGain::Gain16 => adc_gain_ADC_GAIN_16,
+            AdcGain::Gain32 => adc_gain_ADC_GAIN_32,
+            AdcGain::Gain64 => adc_gain_ADC_GAIN_64,
+            AdcGain::Gain128 => adc_gain_ADC_GAIN_128,
+        }
+    }
+
+    /// Undo the gain on `value`, like adc_gain_invert
+    pub fn invert(self, value: i32) -> i32 {
+        match self {
+            AdcGain::Gain1_6 => value * 6,
+            AdcGain::Gain1_5 => value * 5,
+            AdcGain::Gain1_4 => value * 4,
+            AdcGain::Gain1_3 => value * 3,
+            AdcGain::Gain1_2 => value * 2,
+            AdcGain::Gain2_3 => value * 3 / 2,
+            AdcGain::Gain1 => value,
+            AdcGain::Gain2 => value / 2,
+            AdcGain::Gain3 => value / 3,
+            AdcGain::Gain4 => value / 4,
+            AdcGain::Gain8 => value / 8,
+            AdcGain::Gain16 => value / 16,
+            AdcGain::Gain32 => value / 32,
+            AdcGain::Gain64 => value / 64,
+            AdcGain::Gain128 => value / 128,
+        }
+    }
+}
+
+/// Reference voltage for a channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdcReference {
+    Vdd1,
+    Vdd1_2,
+    Vdd1_3,
+    Vdd1_4,
+    /// See `Adc::ref_internal` for the voltage
+    Internal,
+    External0,
+    External1,
+}
+
+impl AdcReference {
+    pub fn raw(self) -> adc_reference {
+        use zephyr_sys::raw::*;
+        match self {
+            AdcReference::Vdd1 => adc_reference_ADC_REF_VDD_1,
+            AdcReference::Vdd1_2 => adc_reference_ADC_REF_VDD_1_2,
+            AdcReference::Vdd1_3 => adc_reference_ADC_REF_VDD_1_3,
+            AdcReference::Vdd1_4 => adc_reference_ADC_REF_VDD_1_4,
+            AdcReference::Internal => adc_reference_ADC_REF_INTERNAL,
+            AdcReference::External0 => adc_reference_ADC_REF_EXTERNAL0,
+            AdcReference::External1 => adc_reference_ADC_REF_EXTERNAL1,
+        }
+    }
+}
+
+/// Sampling time for a channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcquisitionTime {
+    /// The driver's default
+    Default,
+    /// ADC clock ticks
+    Ticks(u16),
+    Microseconds(u16),
+    Nanoseconds(u16),
+}
+
+impl AcquisitionTime {
+    /// Like ADC_ACQ_TIME. The value is limited to 14 bits.
+    pub fn raw(self) -> u16 {
+        let (unit, value) = match self {
+            AcquisitionTime::Default => return ADC_ACQ_TIME_DEFAULT,
+            AcquisitionTime::Ticks(value) => (ADC_ACQ_TIME_TICKS, value),
+            AcquisitionTime::Microseconds(value) => (ADC_ACQ_TIME_MICROSECONDS, value),
+            AcquisitionTime::Nanoseconds(value) => (ADC_ACQ_TIME_NANOSECONDS, value),
+        };
+        (unit << 14) | (value & 0x3fff)
+    }
+}
+
+/// Channel configuration for `Adc::channel_setup`
+#[derive(Clone, Copy, Debug)]
+pub struct AdcChannelCfg {
+    channel_id: u8,
+    gain: AdcGain,
+    reference: AdcReference,
+    acquisition_time: AcquisitionTime,
+    differential: bool,
+    input_positive: u8,
+    input_negative: u8,
+}
+
+impl AdcChannelCfg {
+    /// Single-ended channel with the default acquisition time
+    pub fn new(channel_id: u8, gain: AdcGain, reference: AdcReference) -> Self {
+        AdcChannelCfg {
+            channel_id,
+            gain,
+            reference,
+            acquisition_time: AcquisitionTime::Default,
+            differential: false,
+            input_positive: 0,
+            input_negative: 0,
+        }
+    }
+
+    pub fn acquisition_time(mut self, acquisition_time: AcquisitionTime) -> Self {
+        self.acquisition_time = acquisition_time;
+        self
+    }
+
+    /// Analog input for the channel, on drivers with CONFIG_ADC_CONFIGURABLE_INPUTS
+    pub fn input(mut self, positive: u8) -> Self {
+        self.input_positive = positive;
+        self
+    }
+
+    /// Measure between two inputs. On drivers without configurable inputs, they are ignored and
+    /// the channel determines the pair.
+    pub fn differential(mut self, positive: u8, negative: u8) -> Self {
+        self.differential = true;
+        self.input_positive = positive;
+        self.input_negative = negative;
+        self
+    }
+
+    pub fn channel_id(&self) -> u8 {
+        self.channel_id
+    }
+
+    pub fn gain(&self) -> AdcGain {
+        self.gain
+    }
+
+    pub fn reference(&self) -> AdcReference {
+        self.reference
+    }
+
+    pub fn is_differential(&self) -> bool {
+        self.differential
+    }
+
+    pub fn to_raw(&self) -> adc_channel_cfg {
+        unsafe {
+            let mut cfg = std::mem::MaybeUninit::uninit();
+            rust_adc_channel_cfg_init(
+                cfg.as_mut_ptr(),
+                self.gain.raw(),
+                self.reference.raw(),
+                self.acquisition_time.raw(),
+                self.channel_id,
+                self.differential,
+                self.input_positive,
+                self.input_negative,
+            );
+            cfg.assume_init()
+        }
+    }
+}
+
+/// Convert a raw sample to millivolts, like adc_raw_to_millivolts
+///
+/// `ref_mv` is the reference voltage and `resolution` the resolution of the sequence. For a
+/// differential channel, pass one bit less than the resolution since samples are signed.
+pub fn raw_to_millivolts(raw: i32, ref_mv: i32, gain: AdcGain, resolution: u8) -> i32 {
+    gain.invert(raw * ref_mv) >> resolution
+}
+
+/// The channels to sample and the buffer that receives the samples, like struct adc_sequence
+///
+/// Each read stores one sample per selected channel, in order of channel ID. Holds samples for
+/// resolutions up to 16 bits.
+pub struct AdcSequence<'a> {
+    raw: adc_sequence,
+    _buffer: PhantomData<&'a mut [i16]>,
+}
+
+unsafe impl<'a> Send for AdcSequence<'a> {}
+
+impl<'a> AdcSequence<'a> {
+    /// Sample at `resolution` bits into `buffer`, with no channels selected. Panics if
+    /// `resolution` is over 16 bits, since samples would not fit.
+    pub fn new(buffer: &'a mut [i16], resolution: u8) -> Self {
+        assert!(
+            resolution <= 16,
+            "ADC resolution {} over 16 bits",
+            resolution
+        );
+        AdcSequence {
+            raw: adc_sequence {
+                options: ptr::null(),
+                channels: 0,
+                buffer: buffer.as_mut_ptr() as *mut _,
+                buffer_size: std::mem::size_of_val(buffer) as _,
+                resolution,
+                oversampling: 0,
+                calibrate: false,
+            },
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Add a channel set up with `Adc::channel_setup`
+    pub fn channel(mut self, channel_id: u8) -> Self {
+        assert!(channel_id < 32, "ADC channel {} out of range", channel_id);
+        self.raw.channels |= 1 << channel_id;
+        self
+    }
+
+    /// Set the bitmask of channels
+    pub fn channels(mut self, channels: u32) -> Self {
+        self.raw.channels = channels;
+        self
+    }
+
+    /// Average 2^`oversampling` samples for each result
+    pub fn oversampling(mut self, oversampling: u8) -> Self {
+        self.raw.oversampling = oversampling;
+        self
+    }
+
+    /// Calibrate before sampling, if the driver supports it
+    pub fn calibrate(mut self) -> Self {
+        self.raw.calibrate = true;
+        self
+    }
+
+    pub fn resolution(&self) -> u8 {
+        self.raw.resolution
+    }
+
+    /// Samples written by a read, one for each selected channel in order of channel ID
+    pub fn samples(&self) -> &[i16] {
+        let len = self.raw.buffer_size / std::mem::size_of::<i16>();
+        let written = (self.raw.channels.count_ones() as usize).min(len);
+        unsafe { std::slice::from_raw_parts(self.raw.buffer as *const i16, written) }
+    }
+
+    pub fn as_raw(&self) -> &adc_sequence {
+        &self.raw
+    }
+}
+
+/// An ADC controller
+pub struct Adc<C> {
+    device: &'static Device,
+    _syscalls: PhantomData<C>,
+}
+
+impl<C: AdcSyscalls> Adc<C> {
+    /// # Safety
+    ///
+    /// Caller must ensure the device is an ADC
+    pub unsafe fn new(device: &'static Device) -> Self {
+        Adc {
+            device,
+            _syscalls: PhantomData,
+        }
+    }
+
+    pub fn device(&self) -> &'static Device {
+        self.device
+    }
+
+    fn as_ptr(&self) -> *mut Device {
+        self.device as *const _ as *mut _
+    }
+
+    pub fn channel_setup(&self, channel_cfg: &AdcChannelCfg) -> io::Result<()> {
+        unsafe { C::adc_channel_setup(self.as_ptr(), &channel_cfg.to_raw()) }
+    }
+
+    /// Sample the sequence, blocking until done
+    pub fn read(&self, sequence: &mut AdcSequence) -> io::Result<()> {
+        unsafe { C::adc_read(self.as_ptr(), &sequence.raw) }
+    }
+
+    /// Start sampling the sequence, like adc_read_async. `signal` is raised with the result when
+    /// done. Requires CONFIG_ADC_ASYNC.
+    ///
+    /// # Safety
+    ///
+    /// The sequence and its buffer must not be used, moved, or dropped until `signal` is raised.
+    pub unsafe fn start_read(
+        &self,
+        sequence: &mut AdcSequence,
+        signal: &KPollSignal,
+    ) -> io::Result<()> {
+        C::adc_read_async(self.as_ptr(), &sequence.raw, signal)
+    }
+
+    /// Voltage of `AdcReference::Internal` in millivolts, or 0 if unknown
+    pub fn ref_internal(&self) -> u16 {
+        unsafe { rust_adc_ref_internal(self.device) }
+    }
+}
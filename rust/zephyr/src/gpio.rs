@@ -0,0 +1,445 @@
+use std::io;
+use std::os::raw::c_int;
+
+pub use zephyr_sys::raw::{
+    gpio_callback, gpio_flags_t, gpio_pin_t, gpio_port_pins_t, gpio_port_value_t,
+};
+
+use super::NegErrno;
+use crate::device::Device;
+
+/// Raw syscall API
+pub trait GpioSyscalls {
+    unsafe fn gpio_pin_configure(
+        port: *mut Device,
+        pin: gpio_pin_t,
+        flags: gpio_flags_t,
+    ) -> io::Result<()>;
+    unsafe fn gpio_port_get_raw(port: *mut Device) -> io::Result<gpio_port_value_t>;
+    unsafe fn gpio_port_set_masked_raw(
+        port: *mut Device,
+        mask: gpio_port_pins_t,
+        value: gpio_port_value_t,
+    ) -> io::Result<()>;
+    unsafe fn gpio_port_set_bits_raw(port: *mut Device, pins: gpio_port_pins_t) -> io::Result<()>;
+    unsafe fn gpio_port_clear_bits_raw(port: *mut Device, pins: gpio_port_pins_t)
+        -> io::Result<()>;
+    unsafe fn gpio_port_toggle_bits(port: *mut Device, pins: gpio_port_pins_t) -> io::Result<()>;
+    unsafe fn gpio_pin_interrupt_configure(
+        port: *mut Device,
+        pin: gpio_pin_t,
+        flags: gpio_flags_t,
+    ) -> io::Result<()>;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl GpioSyscalls for $context_struct {
+            #[inline(always)]
+            unsafe fn gpio_pin_configure(
+                port: *mut Device,
+                pin: gpio_pin_t,
+                flags: gpio_flags_t,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::gpio_pin_configure(port, pin, flags)
+                    .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn gpio_port_get_raw(port: *mut Device) -> io::Result<gpio_port_value_t> {
+                let mut value = 0;
+                zephyr_sys::syscalls::$context::gpio_port_get_raw(port, &mut value)
+                    .zero_or_neg_errno()
+                    .map(|_| value)
+            }
+
+            #[inline(always)]
+            unsafe fn gpio_port_set_masked_raw(
+                port: *mut Device,
+                mask: gpio_port_pins_t,
+                value: gpio_port_value_t,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::gpio_port_set_masked_raw(port, mask, value)
+                    .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn gpio_port_set_bits_raw(
+                port: *mut Device,
+                pins: gpio_port_pins_t,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::gpio_port_set_bits_raw(port, pins)
+                    .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn gpio_port_clear_bits_raw(
+                port: *mut Device,
+                pins: gpio_port_pins_t,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::gpio_port_clear_bits_raw(port, pins)
+                    .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn gpio_port_toggle_bits(
+                port: *mut Device,
+                pins: gpio_port_pins_t,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::gpio_port_toggle_bits(port, pins)
+                    .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn gpio_pin_interrupt_configure(
+                port: *mut Device,
+                pin: gpio_pin_t,
+                flags: gpio_flags_t,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::gpio_pin_interrupt_configure(port, pin, flags)
+                    .zero_or_neg_errno()
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+/// GPIO callback API. These are not system calls, so are only implemented for kernel mode.
+pub trait GpioKernelAPI {
+    unsafe fn gpio_add_callback(
+        port: *mut Device,
+        callback: *mut gpio_callback,
+        handler: GpioHandler,
+        pins: gpio_port_pins_t,
+    ) -> io::Result<()>;
+    unsafe fn gpio_remove_callback(port: *mut Device, callback: *mut gpio_callback);
+}
+
+impl GpioKernelAPI for crate::context::Kernel {
+    unsafe fn gpio_add_callback(
+        port: *mut Device,
+        callback: *mut gpio_callback,
+        handler: GpioHandler,
+        pins: gpio_port_pins_t,
+    ) -> io::Result<()> {
+        rust_gpio_add_callback(port, callback, handler, pins).zero_or_neg_errno()
+    }
+
+    unsafe fn gpio_remove_callback(port: *mut Device, callback: *mut gpio_callback) {
+        rust_gpio_remove_callback(port, callback);
+    }
+}
+
+/// Pin configuration flags for `Pin::configure`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GpioFlags(pub gpio_flags_t);
+
+impl GpioFlags {
+    /// Neither input nor output
+    pub const DISCONNECTED: Self = GpioFlags(zephyr_sys::raw::GPIO_DISCONNECTED as _);
+    pub const INPUT: Self = GpioFlags(zephyr_sys::raw::GPIO_INPUT as _);
+    /// Output, keeping the current level
+    pub const OUTPUT: Self = GpioFlags(zephyr_sys::raw::GPIO_OUTPUT as _);
+    /// Output, initially physically low
+    pub const OUTPUT_LOW: Self = GpioFlags(zephyr_sys::raw::GPIO_OUTPUT_LOW as _);
+    /// Output, initially physically high
+    pub const OUTPUT_HIGH: Self = GpioFlags(zephyr_sys::raw::GPIO_OUTPUT_HIGH as _);
+    /// Output, initially logically inactive
+    pub const OUTPUT_INACTIVE: Self = GpioFlags(zephyr_sys::raw::GPIO_OUTPUT_INACTIVE as _);
+    /// Output, initially logically active
+    pub const OUTPUT_ACTIVE: Self = GpioFlags(zephyr_sys::raw::GPIO_OUTPUT_ACTIVE as _);
+    pub const PULL_UP: Self = GpioFlags(zephyr_sys::raw::GPIO_PULL_UP as _);
+    pub const PULL_DOWN: Self = GpioFlags(zephyr_sys::raw::GPIO_PULL_DOWN as _);
+    /// The pin is active when physically low
+    pub const ACTIVE_LOW: Self = GpioFlags(zephyr_sys::raw::GPIO_ACTIVE_LOW as _);
+    pub const OPEN_DRAIN: Self = GpioFlags(zephyr_sys::raw::GPIO_OPEN_DRAIN as _);
+    pub const OPEN_SOURCE: Self = GpioFlags(zephyr_sys::raw::GPIO_OPEN_SOURCE as _);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for GpioFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        GpioFlags(self.0 | rhs.0)
+    }
+}
+
+/// Interrupt trigger for `Pin::interrupt_configure`
+///
+/// Edges and levels are physical. The `*_ACTIVE`/`*_INACTIVE` variants follow `ACTIVE_LOW`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GpioInterrupt(pub gpio_flags_t);
+
+impl GpioInterrupt {
+    pub const DISABLE: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_DISABLE as _);
+    pub const EDGE_RISING: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_EDGE_RISING as _);
+    pub const EDGE_FALLING: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_EDGE_FALLING as _);
+    pub const EDGE_BOTH: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_EDGE_BOTH as _);
+    pub const EDGE_TO_ACTIVE: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_EDGE_TO_ACTIVE as _);
+    pub const EDGE_TO_INACTIVE: Self =
+        GpioInterrupt(zephyr_sys::raw::GPIO_INT_EDGE_TO_INACTIVE as _);
+    pub const LEVEL_LOW: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_LEVEL_LOW as _);
+    pub const LEVEL_HIGH: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_LEVEL_HIGH as _);
+    pub const LEVEL_ACTIVE: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_LEVEL_ACTIVE as _);
+    pub const LEVEL_INACTIVE: Self = GpioInterrupt(zephyr_sys::raw::GPIO_INT_LEVEL_INACTIVE as _);
+}
+
+/// Physical edge to interrupt on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl From<Edge> for GpioInterrupt {
+    fn from(edge: Edge) -> Self {
+        match edge {
+            Edge::Rising => GpioInterrupt::EDGE_RISING,
+            Edge::Falling => GpioInterrupt::EDGE_FALLING,
+            Edge::Both => GpioInterrupt::EDGE_BOTH,
+        }
+    }
+}
+
+/// A GPIO controller
+#[derive(Clone, Copy)]
+pub struct GpioPort(&'static Device);
+
+impl GpioPort {
+    /// # Safety
+    ///
+    /// Caller must ensure the device is a GPIO controller
+    pub unsafe fn new(dev: &'static Device) -> Self {
+        GpioPort(dev)
+    }
+
+    pub fn device(&self) -> &'static Device {
+        self.0
+    }
+
+    fn as_ptr(&self) -> *mut Device {
+        self.0 as *const _ as *mut _
+    }
+
+    /// Pin number `pin` of this port
+    pub fn pin(&self, pin: gpio_pin_t) -> Pin {
+        assert!(pin < 32, "GPIO pin {} out of range", pin);
+        Pin {
+            port: *self,
+            pin,
+            active_low: false,
+        }
+    }
+
+    /// Physical level of every pin
+    #[inline(always)]
+    pub fn get_raw<C: GpioSyscalls>(&self) -> io::Result<gpio_port_value_t> {
+        unsafe { C::gpio_port_get_raw(self.as_ptr()) }
+    }
+
+    /// Set the physical level of the pins in `mask` to the bits in `value`
+    #[inline(always)]
+    pub fn set_masked_raw<C: GpioSyscalls>(
+        &self,
+        mask: gpio_port_pins_t,
+        value: gpio_port_value_t,
+    ) -> io::Result<()> {
+        unsafe { C::gpio_port_set_masked_raw(self.as_ptr(), mask, value) }
+    }
+
+    #[inline(always)]
+    pub fn set_bits_raw<C: GpioSyscalls>(&self, pins: gpio_port_pins_t) -> io::Result<()> {
+        unsafe { C::gpio_port_set_bits_raw(self.as_ptr(), pins) }
+    }
+
+    #[inline(always)]
+    pub fn clear_bits_raw<C: GpioSyscalls>(&self, pins: gpio_port_pins_t) -> io::Result<()> {
+        unsafe { C::gpio_port_clear_bits_raw(self.as_ptr(), pins) }
+    }
+
+    #[inline(always)]
+    pub fn toggle_bits<C: GpioSyscalls>(&self, pins: gpio_port_pins_t) -> io::Result<()> {
+        unsafe { C::gpio_port_toggle_bits(self.as_ptr(), pins) }
+    }
+
+    /// Call `handler` from the GPIO interrupt with the pins in `pins` that triggered it
+    ///
+    /// Interrupts must also be enabled on each pin with `Pin::interrupt_configure`. The handler is
+    /// removed when the returned `GpioCallback` is dropped.
+    pub fn add_callback<C: GpioKernelAPI, F>(
+        &self,
+        pins: gpio_port_pins_t,
+        handler: F,
+    ) -> io::Result<GpioCallback<F>>
+    where
+        F: FnMut(gpio_port_pins_t) + Send + 'static,
+    {
+        let mut data = Box::new(CallbackData {
+            callback: unsafe { std::mem::zeroed() },
+            handler,
+        });
+        unsafe {
+            C::gpio_add_callback(
+                self.as_ptr(),
+                &mut data.callback,
+                callback_trampoline::<F>,
+                pins,
+            )?;
+        }
+        Ok(GpioCallback {
+            port: *self,
+            data: Box::into_raw(data),
+        })
+    }
+}
+
+/// A single pin of a GPIO controller
+///
+/// Remembers whether it was configured `ACTIVE_LOW`, so that `get` and `set` work with the logical
+/// level like gpio_pin_get and gpio_pin_set. `configure` consumes the pin and returns it
+/// configured, and pins can't be cloned, so there is no other copy left behind whose polarity is
+/// out of date.
+pub struct Pin {
+    port: GpioPort,
+    pin: gpio_pin_t,
+    active_low: bool,
+}
+
+impl Pin {
+    pub fn port(&self) -> GpioPort {
+        self.port
+    }
+
+    pub fn number(&self) -> gpio_pin_t {
+        self.pin
+    }
+
+    /// The pin as a bit in a port value
+    pub fn mask(&self) -> gpio_port_pins_t {
+        1 << self.pin
+    }
+
+    #[inline(always)]
+    pub fn configure<C: GpioSyscalls>(self, flags: GpioFlags) -> io::Result<Pin> {
+        unsafe { C::gpio_pin_configure(self.port.as_ptr(), self.pin, flags.0)? };
+        Ok(Pin {
+            active_low: flags.contains(GpioFlags::ACTIVE_LOW),
+            ..self
+        })
+    }
+
+    #[inline(always)]
+    pub fn interrupt_configure<C: GpioSyscalls>(&self, trigger: GpioInterrupt) -> io::Result<()> {
+        unsafe { C::gpio_pin_interrupt_configure(self.port.as_ptr(), self.pin, trigger.0) }
+    }
+
+    /// Physical level
+    #[inline(always)]
+    pub fn get_raw<C: GpioSyscalls>(&self) -> io::Result<bool> {
+        self.port
+            .get_raw::<C>()
+            .map(|value| value & self.mask() != 0)
+    }
+
+    #[inline(always)]
+    pub fn set_raw<C: GpioSyscalls>(&self, high: bool) -> io::Result<()> {
+        if high {
+            self.port.set_bits_raw::<C>(self.mask())
+        } else {
+            self.port.clear_bits_raw::<C>(self.mask())
+        }
+    }
+
+    /// Logical level. True if active.
+    #[inline(always)]
+    pub fn get<C: GpioSyscalls>(&self) -> io::Result<bool> {
+        self.get_raw::<C>().map(|high| high != self.active_low)
+    }
+
+    #[inline(always)]
+    pub fn set<C: GpioSyscalls>(&self, active: bool) -> io::Result<()> {
+        self.set_raw::<C>(active != self.active_low)
+    }
+
+    #[inline(always)]
+    pub fn toggle<C: GpioSyscalls>(&self) -> io::Result<()> {
+        self.port.toggle_bits::<C>(self.mask())
+    }
+
+    /// Like `GpioPort::add_callback` for only this pin. The handler takes no arguments.
+    pub fn add_callback<C: GpioKernelAPI, F>(
+        &self,
+        mut handler: F,
+    ) -> io::Result<GpioCallback<impl FnMut(gpio_port_pins_t) + Send + 'static>>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.port
+            .add_callback::<C, _>(self.mask(), move |_| handler())
+    }
+}
+
+pub type GpioHandler =
+    unsafe extern "C" fn(port: *const Device, callback: *mut gpio_callback, pins: gpio_port_pins_t);
+
+extern "C" {
+    // From rust-gpio.c
+    fn rust_gpio_add_callback(
+        port: *mut Device,
+        callback: *mut gpio_callback,
+        handler: GpioHandler,
+        pin_mask: gpio_port_pins_t,
+    ) -> c_int;
+    fn rust_gpio_remove_callback(port: *mut Device, callback: *mut gpio_callback) -> c_int;
+}
+
+#[repr(C)]
+struct CallbackData<F> {
+    // First, so the kernel's pointer to it is also a pointer to the whole struct
+    callback: gpio_callback,
+    handler: F,
+}
+
+unsafe extern "C" fn callback_trampoline<F>(
+    _port: *const Device,
+    callback: *mut gpio_callback,
+    pins: gpio_port_pins_t,
+) where
+    F: FnMut(gpio_port_pins_t) + Send + 'static,
+{
+    let data = callback as *mut CallbackData<F>;
+    ((*data).handler)(pins)
+}
+
+/// A registered GPIO interrupt handler
+///
+/// The handler runs in interrupt context. It is moved into a heap allocation together with the
+/// gpio_callback the driver keeps a pointer to, so it must be `Send`. Dropping this removes the
+/// callback before freeing the handler, which like adding it is only possible in kernel mode.
+pub struct GpioCallback<F> {
+    port: GpioPort,
+    data: *mut CallbackData<F>,
+}
+
+unsafe impl<F: Send> Send for GpioCallback<F> {}
+unsafe impl<F: Send> Sync for GpioCallback<F> {}
+
+impl<F> Drop for GpioCallback<F> {
+    fn drop(&mut self) {
+        unsafe {
+            crate::context::Kernel::gpio_remove_callback(
+                self.port.as_ptr(),
+                &mut (*self.data).callback,
+            );
+            drop(Box::from_raw(self.data));
+        }
+    }
+}
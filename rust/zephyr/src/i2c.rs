@@ -0,0 +1,263 @@
+use std::io;
+use std::marker::PhantomData;
+
+#[cfg(feature = "embedded-hal")]
+use embedded_hal::i2c::{self as hal, Operation, SevenBitAddress, TenBitAddress};
+pub use zephyr_sys::raw::i2c_msg;
+
+use super::NegErrno;
+use crate::device::Device;
+#[cfg(feature = "embedded-hal")]
+use crate::HalError;
+
+// Flags from drivers/i2c.h. Defined with BIT(), so not in the bindings.
+pub const I2C_MSG_WRITE: u8 = 0;
+pub const I2C_MSG_READ: u8 = 1 << 0;
+pub const I2C_MSG_STOP: u8 = 1 << 1;
+pub const I2C_MSG_RESTART: u8 = 1 << 2;
+pub const I2C_MSG_ADDR_10_BITS: u8 = 1 << 3;
+
+pub const I2C_ADDR_10_BITS: u32 = 1 << 0;
+pub const I2C_SPEED_SHIFT: u32 = 1;
+pub const I2C_SPEED_MASK: u32 = 0x7 << I2C_SPEED_SHIFT;
+/// I2C_MODE_CONTROLLER, formerly I2C_MODE_MASTER
+pub const I2C_MODE_CONTROLLER: u32 = 1 << 4;
+
+/// Raw syscall API
+pub trait I2cSyscalls {
+    unsafe fn i2c_configure(device: *mut Device, dev_config: u32) -> io::Result<()>;
+    unsafe fn i2c_transfer(device: *mut Device, msgs: &mut [i2c_msg], addr: u16) -> io::Result<()>;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl I2cSyscalls for $context_struct {
+            #[inline(always)]
+            unsafe fn i2c_configure(device: *mut Device, dev_config: u32) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::i2c_configure(device, dev_config)
+                    .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn i2c_transfer(
+                device: *mut Device,
+                msgs: &mut [i2c_msg],
+                addr: u16,
+            ) -> io::Result<()> {
+                if msgs.len() > u8::MAX as usize {
+                    return Err(io::ErrorKind::InvalidInput.into());
+                }
+                zephyr_sys::syscalls::$context::i2c_transfer(
+                    device,
+                    msgs.as_mut_ptr(),
+                    msgs.len() as u8,
+                    addr,
+                )
+                .zero_or_neg_errno()
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+/// Bus speed for `I2c::configure`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum I2cSpeed {
+    /// 100 kHz
+    Standard = 1,
+    /// 400 kHz
+    Fast = 2,
+    /// 1 MHz
+    FastPlus = 3,
+    /// 3.4 MHz
+    High = 4,
+    /// 5 MHz
+    Ultra = 5,
+}
+
+/// One message of a transfer, borrowing its buffer
+#[repr(transparent)]
+pub struct I2cMsg<'a>(i2c_msg, PhantomData<&'a mut [u8]>);
+
+impl<'a> I2cMsg<'a> {
+    /// Write `buf`. The driver only reads from the buffer of a write message.
+    pub fn write(buf: &'a [u8]) -> Self {
+        Self::new(buf.as_ptr() as *mut u8, buf.len(), I2C_MSG_WRITE)
+    }
+
+    /// Read into `buf`
+    pub fn read(buf: &'a mut [u8]) -> Self {
+        Self::new(buf.as_mut_ptr(), buf.len(), I2C_MSG_READ)
+    }
+
+    fn new(buf: *mut u8, len: usize, flags: u8) -> Self {
+        I2cMsg(
+            i2c_msg {
+                buf: buf as _,
+                len: len as _,
+                flags,
+            },
+            PhantomData,
+        )
+    }
+
+    /// Send a STOP after this message
+    pub fn stop(mut self) -> Self {
+        self.0.flags |= I2C_MSG_STOP;
+        self
+    }
+
+    /// Send a RESTART before this message. Without it, a message continues the previous one.
+    pub fn restart(mut self) -> Self {
+        self.0.flags |= I2C_MSG_RESTART;
+        self
+    }
+
+    /// Address the target with a 10-bit address
+    pub fn addr_10_bits(mut self) -> Self {
+        self.0.flags |= I2C_MSG_ADDR_10_BITS;
+        self
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.0.flags
+    }
+}
+
+/// An I2C controller
+///
+/// The syscall context is part of the type so that it can implement the `embedded-hal` `I2c`
+/// trait with the `embedded-hal` feature, e.g. `I2c<zephyr::context::Any>`.
+pub struct I2c<C> {
+    device: &'static Device,
+    _syscalls: PhantomData<C>,
+}
+
+impl<C: I2cSyscalls> I2c<C> {
+    /// # Safety
+    ///
+    /// Caller must ensure the device is an I2C controller
+    pub unsafe fn new(device: &'static Device) -> Self {
+        I2c {
+            device,
+            _syscalls: PhantomData,
+        }
+    }
+
+    pub fn device(&self) -> &'static Device {
+        self.device
+    }
+
+    fn as_ptr(&self) -> *mut Device {
+        self.device as *const _ as *mut _
+    }
+
+    /// Operate as a controller at `speed`
+    pub fn configure(&self, speed: I2cSpeed) -> io::Result<()> {
+        self.configure_raw(I2C_MODE_CONTROLLER | (speed as u32) << I2C_SPEED_SHIFT)
+    }
+
+    /// Configure with I2C_* flags as for i2c_configure
+    pub fn configure_raw(&self, dev_config: u32) -> io::Result<()> {
+        unsafe { C::i2c_configure(self.as_ptr(), dev_config) }
+    }
+
+    /// Transfer `msgs` to or from the target at `addr`
+    pub fn transfer(&self, msgs: &mut [I2cMsg], addr: u16) -> io::Result<()> {
+        unsafe {
+            C::i2c_transfer(
+                self.as_ptr(),
+                &mut *(msgs as *mut [I2cMsg] as *mut [i2c_msg]),
+                addr,
+            )
+        }
+    }
+
+    /// Like i2c_write
+    pub fn write(&self, addr: u16, buf: &[u8]) -> io::Result<()> {
+        self.transfer(&mut [I2cMsg::write(buf).stop()], addr)
+    }
+
+    /// Like i2c_read
+    pub fn read(&self, addr: u16, buf: &mut [u8]) -> io::Result<()> {
+        self.transfer(&mut [I2cMsg::read(buf).stop()], addr)
+    }
+
+    /// Like i2c_write_read: write, then read after a RESTART
+    pub fn write_read(&self, addr: u16, write: &[u8], read: &mut [u8]) -> io::Result<()> {
+        self.transfer(
+            &mut [I2cMsg::write(write), I2cMsg::read(read).restart().stop()],
+            addr,
+        )
+    }
+
+    /// Adjacent operations in the same direction are merged into one, with a RESTART only when the
+    /// direction changes, as `embedded-hal` requires.
+    #[cfg(feature = "embedded-hal")]
+    fn hal_transaction(
+        &self,
+        addr: u16,
+        ten_bit: bool,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), HalError> {
+        let mut msgs = Vec::with_capacity(operations.len());
+        let mut prev_read = None;
+        for op in operations.iter_mut() {
+            let (mut msg, read) = match op {
+                Operation::Read(buf) => (I2cMsg::read(buf), true),
+                Operation::Write(buf) => (I2cMsg::write(buf), false),
+            };
+            if prev_read == Some(!read) {
+                msg = msg.restart();
+            }
+            if ten_bit {
+                msg = msg.addr_10_bits();
+            }
+            prev_read = Some(read);
+            msgs.push(msg);
+        }
+        match msgs.pop() {
+            Some(last) => msgs.push(last.stop()),
+            None => return Ok(()),
+        }
+        Ok(self.transfer(&mut msgs, addr)?)
+    }
+}
+
+/// Zephyr drivers report most failures, including a NACK, as EIO, so the kind is always `Other`
+#[cfg(feature = "embedded-hal")]
+impl hal::Error for HalError {
+    fn kind(&self) -> hal::ErrorKind {
+        hal::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<C> hal::ErrorType for I2c<C> {
+    type Error = HalError;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<C: I2cSyscalls> hal::I2c<SevenBitAddress> for I2c<C> {
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), HalError> {
+        self.hal_transaction(address.into(), false, operations)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<C: I2cSyscalls> hal::I2c<TenBitAddress> for I2c<C> {
+    fn transaction(
+        &mut self,
+        address: TenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), HalError> {
+        self.hal_transaction(address, true, operations)
+    }
+}
@@ -1,13 +1,60 @@
 extern crate zephyr_core;
 extern crate zephyr_sys;
 
+use std::fmt;
 use std::io;
 
 pub use zephyr_core::*;
+pub mod adc;
 pub mod device;
 pub mod eeprom;
+pub mod gpio;
+pub mod i2c;
+pub mod pipe;
+pub mod spi;
 pub mod uart;
 
+/// Conversion to `io::Error`. A `From` impl is only possible where `std` is a dependency of
+/// zephyr-core, which it is not when zephyr-core is part of the sysroot.
+pub trait IntoIoError {
+    fn into_io_error(self) -> io::Error;
+}
+
+impl IntoIoError for Error {
+    fn into_io_error(self) -> io::Error {
+        io::Error::from_raw_os_error(self.errno())
+    }
+}
+
+/// Error type for the `embedded-hal` trait impls, which need a type local to this crate
+#[cfg(feature = "embedded-hal")]
+#[derive(Debug)]
+pub struct HalError(pub io::Error);
+
+#[cfg(feature = "embedded-hal")]
+impl fmt::Display for HalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl std::error::Error for HalError {}
+
+#[cfg(feature = "embedded-hal")]
+impl From<io::Error> for HalError {
+    fn from(e: io::Error) -> Self {
+        HalError(e)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl From<HalError> for io::Error {
+    fn from(e: HalError) -> Self {
+        e.0
+    }
+}
+
 trait NegErrno: NegErr {
     fn neg_errno(&self) -> io::Result<u32>;
     fn zero_or_neg_errno(&self) -> io::Result<()>;
@@ -15,8 +62,7 @@ trait NegErrno: NegErr {
 
 impl NegErrno for i32 {
     fn neg_errno(&self) -> io::Result<u32> {
-        self.neg_err()
-            .map_err(|e| io::Error::from_raw_os_error(e as i32))
+        self.neg_err().map_err(Error::into_io_error)
     }
 
     fn zero_or_neg_errno(&self) -> io::Result<()> {
@@ -0,0 +1,110 @@
+use std::ffi::c_void;
+use std::io;
+use std::marker::PhantomData;
+
+use super::NegErrno;
+pub use zephyr_core::pipe::*;
+
+/// Byte stream over a k_pipe
+///
+/// Every read or write transfers at least `min_xfer` bytes (capped to the buffer length), waiting
+/// up to `timeout` for them. The defaults of one byte and `K_FOREVER` give the blocking behavior
+/// expected of `io::Read` and `io::Write`. If the timeout expires after some bytes were
+/// transferred, the partial count is returned rather than an error. A pipe never reaches EOF, so
+/// a transfer of nothing is an error, `WouldBlock` with a `min_xfer` of zero.
+pub struct Pipe<'a, C> {
+    pipe: &'a KPipe,
+    min_xfer: usize,
+    timeout: zephyr_core::Timeout,
+    _syscalls: PhantomData<C>,
+}
+
+impl<'a, C: PipeSyscalls> Pipe<'a, C> {
+    pub fn new(pipe: &'a KPipe) -> Self {
+        Pipe {
+            pipe,
+            min_xfer: 1,
+            timeout: zephyr_core::K_FOREVER,
+            _syscalls: PhantomData,
+        }
+    }
+
+    /// Minimum number of bytes each transfer must move. Zero returns immediately with whatever
+    /// could be transferred, or `WouldBlock` if nothing could.
+    pub fn set_min_xfer(&mut self, min_xfer: usize) {
+        self.min_xfer = min_xfer;
+    }
+
+    pub fn min_xfer(&self) -> usize {
+        self.min_xfer
+    }
+
+    /// How long each transfer waits for `min_xfer` bytes
+    pub fn set_timeout(&mut self, timeout: zephyr_core::Timeout) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> zephyr_core::Timeout {
+        self.timeout
+    }
+
+    pub fn kobj(&self) -> &'a KPipe {
+        self.pipe
+    }
+
+    /// Keep the byte count if anything was transferred before the kernel gave up. Nothing
+    /// transferred is `WouldBlock` rather than `Ok(0)`, which would mean EOF to `io::Read` and
+    /// fail `write_all`. The kernel reports that as success when `min_xfer` is zero.
+    fn xfer_result(rc: i32, count: usize) -> io::Result<usize> {
+        match rc.neg_errno() {
+            _ if count > 0 => Ok(count),
+            Ok(_) => Err(io::ErrorKind::WouldBlock.into()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<'a, C: PipeSyscalls> io::Read for Pipe<'a, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        let rc = unsafe {
+            C::k_pipe_get(
+                self.pipe,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                &mut count,
+                self.min_xfer.min(buf.len()),
+                self.timeout.0,
+            )
+        };
+        Self::xfer_result(rc, count)
+    }
+}
+
+impl<'a, C: PipeSyscalls> io::Write for Pipe<'a, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        let rc = unsafe {
+            C::k_pipe_put(
+                self.pipe,
+                buf.as_ptr() as *const c_void,
+                buf.len(),
+                &mut count,
+                self.min_xfer.min(buf.len()),
+                self.timeout.0,
+            )
+        };
+        Self::xfer_result(rc, count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Written bytes are already in the pipe or handed to a reader
+        Ok(())
+    }
+}
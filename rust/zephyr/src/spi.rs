@@ -0,0 +1,458 @@
+use std::io;
+use std::marker::PhantomData;
+use std::ptr;
+
+#[cfg(feature = "embedded-hal")]
+use embedded_hal::spi::{self as hal, Operation, Phase, Polarity};
+pub use zephyr_sys::raw::{spi_buf, spi_buf_set, spi_config, spi_cs_control};
+
+use super::NegErrno;
+use crate::device::Device;
+use crate::gpio::{self, gpio_pin_t, GpioFlags};
+#[cfg(feature = "embedded-hal")]
+use crate::HalError;
+
+// spi_config operation flags from drivers/spi.h. Defined with BIT(), so not in the bindings.
+pub const SPI_OP_MODE_SLAVE: u32 = 1 << 0;
+pub const SPI_MODE_CPOL: u32 = 1 << 1;
+pub const SPI_MODE_CPHA: u32 = 1 << 2;
+pub const SPI_MODE_LOOP: u32 = 1 << 3;
+pub const SPI_TRANSFER_LSB: u32 = 1 << 4;
+pub const SPI_WORD_SIZE_SHIFT: u32 = 5;
+pub const SPI_WORD_SIZE_MASK: u32 = 0x3f << SPI_WORD_SIZE_SHIFT;
+pub const SPI_HOLD_ON_CS: u32 = 1 << 12;
+pub const SPI_LOCK_ON: u32 = 1 << 13;
+pub const SPI_CS_ACTIVE_HIGH: u32 = 1 << 14;
+
+/// Raw syscall API
+pub trait SpiSyscalls {
+    unsafe fn spi_transceive(
+        device: *mut Device,
+        config: *const spi_config,
+        tx_bufs: Option<&spi_buf_set>,
+        rx_bufs: Option<&spi_buf_set>,
+    ) -> io::Result<()>;
+    unsafe fn spi_release(device: *mut Device, config: *const spi_config) -> io::Result<()>;
+}
+
+macro_rules! trait_impl {
+    ($context:ident, $context_struct:path) => {
+        impl SpiSyscalls for $context_struct {
+            #[inline(always)]
+            unsafe fn spi_transceive(
+                device: *mut Device,
+                config: *const spi_config,
+                tx_bufs: Option<&spi_buf_set>,
+                rx_bufs: Option<&spi_buf_set>,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::spi_transceive(
+                    device,
+                    config,
+                    tx_bufs.map_or(ptr::null(), |bufs| bufs),
+                    rx_bufs.map_or(ptr::null(), |bufs| bufs),
+                )
+                .zero_or_neg_errno()
+            }
+
+            #[inline(always)]
+            unsafe fn spi_release(
+                device: *mut Device,
+                config: *const spi_config,
+            ) -> io::Result<()> {
+                zephyr_sys::syscalls::$context::spi_release(device, config).zero_or_neg_errno()
+            }
+        }
+    };
+}
+
+trait_impl!(kernel, crate::context::Kernel);
+trait_impl!(user, crate::context::User);
+trait_impl!(any, crate::context::Any);
+
+extern "C" {
+    // From rust-spi.c
+    fn rust_spi_cs_control_init(
+        cs: *mut spi_cs_control,
+        port: *const Device,
+        pin: gpio_pin_t,
+        dt_flags: u32,
+        delay: u32,
+    );
+}
+
+struct RawConfig {
+    config: spi_config,
+    cs: spi_cs_control,
+}
+
+/// A spi_config together with its chip select control
+///
+/// Drivers recognize a configuration they have already applied by its address, so it is kept in a
+/// heap allocation that does not move. Create one with `SpiConfigBuilder`.
+pub struct SpiConfig(Box<RawConfig>);
+
+unsafe impl Send for SpiConfig {}
+unsafe impl Sync for SpiConfig {}
+
+impl SpiConfig {
+    pub fn builder(frequency: u32) -> SpiConfigBuilder {
+        SpiConfigBuilder::new(frequency)
+    }
+
+    pub fn as_ptr(&self) -> *const spi_config {
+        &self.0.config
+    }
+
+    pub fn frequency(&self) -> u32 {
+        self.0.config.frequency
+    }
+
+    pub fn operation(&self) -> u32 {
+        self.0.config.operation as u32
+    }
+
+    #[cfg(feature = "embedded-hal")]
+    fn set_operation(&mut self, operation: u32) {
+        self.0.config.operation = operation as _;
+    }
+}
+
+/// Builder for `SpiConfig`. Defaults to a controller in mode 0 with 8-bit words, MSB first, and
+/// no chip select GPIO.
+pub struct SpiConfigBuilder {
+    frequency: u32,
+    operation: u32,
+    slave: u16,
+    cs: Option<(gpio::Pin, GpioFlags)>,
+    cs_delay_us: u32,
+}
+
+impl SpiConfigBuilder {
+    /// Clock at `frequency` Hz
+    pub fn new(frequency: u32) -> Self {
+        SpiConfigBuilder {
+            frequency,
+            operation: 8 << SPI_WORD_SIZE_SHIFT,
+            slave: 0,
+            cs: None,
+            cs_delay_us: 0,
+        }
+    }
+
+    /// Clock polarity and phase. Without the `embedded-hal` feature, add SPI_MODE_CPOL and
+    /// SPI_MODE_CPHA with `operation`.
+    #[cfg(feature = "embedded-hal")]
+    pub fn mode(mut self, mode: hal::Mode) -> Self {
+        self.operation &= !(SPI_MODE_CPOL | SPI_MODE_CPHA);
+        if mode.polarity == Polarity::IdleHigh {
+            self.operation |= SPI_MODE_CPOL;
+        }
+        if mode.phase == Phase::CaptureOnSecondTransition {
+            self.operation |= SPI_MODE_CPHA;
+        }
+        self
+    }
+
+    /// Bits per word
+    pub fn word_size(mut self, bits: u8) -> Self {
+        self.operation &= !SPI_WORD_SIZE_MASK;
+        self.operation |= (bits as u32) << SPI_WORD_SIZE_SHIFT & SPI_WORD_SIZE_MASK;
+        self
+    }
+
+    pub fn lsb_first(mut self) -> Self {
+        self.operation |= SPI_TRANSFER_LSB;
+        self
+    }
+
+    /// Connect MOSI to MISO inside the controller, if supported
+    pub fn loopback(mut self) -> Self {
+        self.operation |= SPI_MODE_LOOP;
+        self
+    }
+
+    /// Add SPI_* operation flags not covered by the other methods
+    pub fn operation(mut self, flags: u32) -> Self {
+        self.operation |= flags;
+        self
+    }
+
+    /// Hardware chip select line, for controllers that have several
+    pub fn slave(mut self, slave: u16) -> Self {
+        self.slave = slave;
+        self
+    }
+
+    /// Drive `pin` as chip select. `flags` are the devicetree GPIO flags, usually
+    /// `GpioFlags::ACTIVE_LOW`.
+    pub fn cs_gpio(mut self, pin: gpio::Pin, flags: GpioFlags) -> Self {
+        self.cs = Some((pin, flags));
+        self
+    }
+
+    /// Wait between asserting chip select and starting the clock, and after the transfer
+    pub fn cs_delay_us(mut self, delay: u32) -> Self {
+        self.cs_delay_us = delay;
+        self
+    }
+
+    pub fn build(self) -> SpiConfig {
+        let mut raw = Box::new(RawConfig {
+            config: unsafe { std::mem::zeroed() },
+            cs: unsafe { std::mem::zeroed() },
+        });
+        raw.config.frequency = self.frequency as _;
+        raw.config.operation = self.operation as _;
+        raw.config.slave = self.slave as _;
+        if let Some((pin, flags)) = self.cs {
+            unsafe {
+                rust_spi_cs_control_init(
+                    &mut raw.cs,
+                    pin.port().device(),
+                    pin.number(),
+                    flags.0,
+                    self.cs_delay_us,
+                );
+            }
+            raw.config.cs = &raw.cs;
+        }
+        SpiConfig(raw)
+    }
+}
+
+fn tx_bufs(tx: &[&[u8]]) -> Vec<spi_buf> {
+    tx.iter()
+        .map(|buf| spi_buf {
+            buf: buf.as_ptr() as *mut _,
+            len: buf.len() as _,
+        })
+        .collect()
+}
+
+fn rx_bufs(rx: &mut [&mut [u8]]) -> Vec<spi_buf> {
+    rx.iter_mut()
+        .map(|buf| spi_buf {
+            buf: buf.as_mut_ptr() as *mut _,
+            len: buf.len() as _,
+        })
+        .collect()
+}
+
+/// Clock out `len` NOP words, or discard `len` words read
+#[cfg(feature = "embedded-hal")]
+fn nop_buf(len: usize) -> spi_buf {
+    spi_buf {
+        buf: ptr::null_mut(),
+        len: len as _,
+    }
+}
+
+fn buf_set(bufs: &[spi_buf]) -> Option<spi_buf_set> {
+    if bufs.is_empty() {
+        None
+    } else {
+        Some(spi_buf_set {
+            buffers: bufs.as_ptr(),
+            count: bufs.len() as _,
+        })
+    }
+}
+
+/// An SPI controller with the configuration of one attached device
+///
+/// The syscall context is part of the type so that it can implement the `embedded-hal` traits,
+/// e.g. `Spi<zephyr::context::Any>`, with the `embedded-hal` feature. With a chip select GPIO in
+/// the config it is a `SpiDevice`. Without one it can also be used as a `SpiBus`, with chip
+/// select managed by the caller.
+pub struct Spi<C> {
+    device: &'static Device,
+    config: SpiConfig,
+    _syscalls: PhantomData<C>,
+}
+
+impl<C: SpiSyscalls> Spi<C> {
+    /// # Safety
+    ///
+    /// Caller must ensure the device is an SPI controller
+    pub unsafe fn new(device: &'static Device, config: SpiConfig) -> Self {
+        Spi {
+            device,
+            config,
+            _syscalls: PhantomData,
+        }
+    }
+
+    pub fn device(&self) -> &'static Device {
+        self.device
+    }
+
+    pub fn config(&self) -> &SpiConfig {
+        &self.config
+    }
+
+    fn as_ptr(&self) -> *mut Device {
+        self.device as *const _ as *mut _
+    }
+
+    /// Write the `tx` buffers in order while reading into the `rx` buffers in order
+    ///
+    /// If the total lengths differ, the controller clocks out NOP words past the end of `tx` or
+    /// discards words past the end of `rx`.
+    pub fn transceive(&self, tx: &[&[u8]], rx: &mut [&mut [u8]]) -> io::Result<()> {
+        self.transceive_bufs(&tx_bufs(tx), &rx_bufs(rx))
+    }
+
+    fn transceive_bufs(&self, tx: &[spi_buf], rx: &[spi_buf]) -> io::Result<()> {
+        unsafe {
+            C::spi_transceive(
+                self.as_ptr(),
+                self.config.as_ptr(),
+                buf_set(tx).as_ref(),
+                buf_set(rx).as_ref(),
+            )
+        }
+    }
+
+    /// Like spi_write
+    pub fn write(&self, tx: &[&[u8]]) -> io::Result<()> {
+        self.transceive(tx, &mut [])
+    }
+
+    /// Like spi_read
+    pub fn read(&self, rx: &mut [&mut [u8]]) -> io::Result<()> {
+        self.transceive(&[], rx)
+    }
+
+    /// Release the bus after transfers with SPI_LOCK_ON or SPI_HOLD_ON_CS
+    pub fn release(&self) -> io::Result<()> {
+        unsafe { C::spi_release(self.as_ptr(), self.config.as_ptr()) }
+    }
+}
+
+/// Zephyr drivers do not distinguish SPI errors, so the kind is always `Other`
+#[cfg(feature = "embedded-hal")]
+impl hal::Error for HalError {
+    fn kind(&self) -> hal::ErrorKind {
+        hal::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<C> hal::ErrorType for Spi<C> {
+    type Error = HalError;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<C: SpiSyscalls> hal::SpiBus for Spi<C> {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), HalError> {
+        Ok(Spi::read(self, &mut [words])?)
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), HalError> {
+        Ok(Spi::write(self, &[words])?)
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), HalError> {
+        Ok(self.transceive(&[write], &mut [read])?)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), HalError> {
+        // Not every driver can transmit and receive from the same buffer
+        let write = words.to_vec();
+        Ok(self.transceive(&[&write], &mut [words])?)
+    }
+
+    /// Transfers complete before returning
+    fn flush(&mut self) -> Result<(), HalError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<C: SpiSyscalls> Spi<C> {
+    /// Run operations as one spi_transceive, so chip select stays asserted between them even
+    /// where the driver ignores SPI_HOLD_ON_CS. Each operation gets a buffer in both directions,
+    /// with NOP buffers filling in for the missing side.
+    fn transceive_ops(&self, operations: &mut [Operation<'_, u8>]) -> io::Result<()> {
+        let mut tx = Vec::with_capacity(operations.len());
+        let mut rx = Vec::with_capacity(operations.len());
+        // Not every driver can transmit and receive from the same buffer
+        let mut copies = Vec::new();
+        for op in operations.iter_mut() {
+            match op {
+                Operation::Read(buf) => {
+                    tx.push(nop_buf(buf.len()));
+                    rx.extend(rx_bufs(&mut [buf]));
+                }
+                Operation::Write(buf) => {
+                    tx.extend(tx_bufs(&[buf]));
+                    rx.push(nop_buf(buf.len()));
+                }
+                Operation::Transfer(read, write) => {
+                    // Split the longer side so both directions stay in step for the next
+                    // operation
+                    let len = read.len().min(write.len());
+                    let (read, read_rest) = read.split_at_mut(len);
+                    let (write, write_rest) = write.split_at(len);
+                    if len > 0 {
+                        tx.extend(tx_bufs(&[write]));
+                        rx.extend(rx_bufs(&mut [read]));
+                    }
+                    if !read_rest.is_empty() {
+                        tx.push(nop_buf(read_rest.len()));
+                        rx.extend(rx_bufs(&mut [read_rest]));
+                    } else if !write_rest.is_empty() {
+                        tx.extend(tx_bufs(&[write_rest]));
+                        rx.push(nop_buf(write_rest.len()));
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    copies.push(buf.to_vec());
+                    tx.extend(tx_bufs(&[copies.last().unwrap()]));
+                    rx.extend(rx_bufs(&mut [buf]));
+                }
+                Operation::DelayNs(_) => unreachable!("delays split transactions"),
+            }
+        }
+        self.transceive_bufs(&tx, &rx)
+    }
+
+    /// Operations between delays are transferred together. The bus is held across the delays.
+    fn run_transaction(&self, operations: &mut [Operation<'_, u8>]) -> io::Result<()> {
+        let mut rest = operations;
+        loop {
+            let end = rest
+                .iter()
+                .position(|op| matches!(op, Operation::DelayNs(_)))
+                .unwrap_or(rest.len());
+            let (ops, tail) = rest.split_at_mut(end);
+            if !ops.is_empty() {
+                self.transceive_ops(ops)?;
+            }
+            match tail.split_first_mut() {
+                Some((Operation::DelayNs(ns), tail)) => {
+                    let us = *ns / 1000 + (*ns % 1000 != 0) as u32;
+                    unsafe { zephyr_sys::syscalls::any::k_busy_wait(us) };
+                    rest = tail;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<C: SpiSyscalls> hal::SpiDevice for Spi<C> {
+    /// Chip select stays asserted and other users are locked out of the bus until all operations
+    /// are done
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), HalError> {
+        let operation = self.config.operation();
+        self.config
+            .set_operation(operation | SPI_HOLD_ON_CS | SPI_LOCK_ON);
+        let result = self.run_transaction(operations);
+        self.config.set_operation(operation);
+        let released = self.release();
+        result?;
+        Ok(released?)
+    }
+}
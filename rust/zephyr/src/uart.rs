@@ -1,16 +1,17 @@
 use super::NegErr;
 use crate::device::Device;
+use crate::Error;
 
 pub trait UartSyscalls {
     fn uart_poll_out(device: &Device, out_char: char);
 
-    fn uart_poll_in(device: &Device) -> Result<Option<char>, u32>;
+    fn uart_poll_in(device: &Device) -> Result<Option<char>, Error>;
 
-    fn uart_err_check(device: &Device) -> Option<u32>;
+    fn uart_err_check(device: &Device) -> Option<Error>;
 
-    fn uart_config_get(device: &Device) -> Result<UartConfig, u32>;
+    fn uart_config_get(device: &Device) -> Result<UartConfig, Error>;
 
-    fn uart_configure(device: &Device, config: &UartConfig) -> Result<(), u32>;
+    fn uart_configure(device: &Device, config: &UartConfig) -> Result<(), Error>;
 }
 
 macro_rules! trait_impl {
@@ -27,7 +28,7 @@ macro_rules! trait_impl {
             }
 
             #[inline(always)]
-            fn uart_poll_in(device: &Device) -> Result<Option<char>, u32> {
+            fn uart_poll_in(device: &Device) -> Result<Option<char>, Error> {
                 let mut munge: u8 = 0;
                 let rc = unsafe {
                     zephyr_sys::syscalls::$context::uart_poll_in(
@@ -41,13 +42,13 @@ macro_rules! trait_impl {
                 // remap a return value of -1 from uart_poll_in() to Ok(None)
                 match rc {
                     Ok(c) => Ok(Some(c)),
-                    Err(1) => Ok(None),
+                    Err(Error::NotPermitted) => Ok(None),
                     Err(e) => Err(e),
                 }
             }
 
             #[inline(always)]
-            fn uart_err_check(device: &Device) -> Option<u32> {
+            fn uart_err_check(device: &Device) -> Option<Error> {
                 let rc = unsafe {
                     zephyr_sys::syscalls::$context::uart_err_check(device as *const _ as *mut _)
                 }
@@ -60,7 +61,7 @@ macro_rules! trait_impl {
             }
 
             #[inline(always)]
-            fn uart_config_get(device: &Device) -> Result<UartConfig, u32> {
+            fn uart_config_get(device: &Device) -> Result<UartConfig, Error> {
                 let mut config = UartConfig::default();
                 unsafe {
                     zephyr_sys::syscalls::$context::uart_config_get(
@@ -73,7 +74,7 @@ macro_rules! trait_impl {
             }
 
             #[inline(always)]
-            fn uart_configure(device: &Device, config: &UartConfig) -> Result<(), u32> {
+            fn uart_configure(device: &Device, config: &UartConfig) -> Result<(), Error> {
                 unsafe {
                     zephyr_sys::syscalls::$context::uart_configure(
                         device as *const _ as *mut _,
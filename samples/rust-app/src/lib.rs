@@ -10,7 +10,6 @@ extern crate zephyr_logger;
 use std::cell::RefCell;
 use std::time::Duration;
 
-use core::ffi::c_void;
 use log::LevelFilter;
 
 use zephyr::device::DeviceSyscalls;
@@ -22,6 +21,7 @@ thread_local!(static TLS: RefCell<u8> = RefCell::new(1));
 
 zephyr_macros::k_mutex_define!(MUTEX);
 zephyr_macros::k_sem_define!(TLS_SEM, 0, 1);
+zephyr_macros::static_mutex!(COUNTER: u32 = 0);
 
 fn mutex_test() {
     let data = 1u32;
@@ -35,6 +35,9 @@ fn mutex_test() {
     zephyr::any::k_str_out("Locking\n");
     let _val = mutex.lock::<zephyr::context::Any>();
     zephyr::any::k_str_out("Unlocking\n");
+
+    // Static data needs no unsafe
+    *COUNTER.lock::<zephyr::context::Any>() += 1;
 }
 
 fn std_mutex_test() {
@@ -46,15 +49,14 @@ fn std_mutex_test() {
 
 fn thread_join_std_mem_domain(_context: zephyr::context::Kernel) {
     use zephyr::context::Kernel as C;
-    zephyr::static_mem_domain!(rust_std_domain).add_thread::<C>(C::k_current_get());
+    zephyr::static_mem_domain!(rust_std_domain)
+        .add_thread::<C>(C::k_current_get())
+        .expect("add thread to rust_std_domain");
 }
 
-#[no_mangle]
-pub extern "C" fn rust_second_thread(
-    _a: *const c_void,
-    _b: *const c_void,
-    _c: *const c_void,
-) {
+zephyr_macros::k_thread_define!(SECOND_THREAD, second_thread, stack = 1024, priority = 5);
+
+fn second_thread() {
     thread_join_std_mem_domain(zephyr::context::Kernel);
 
     println!("Hello from second thread");
@@ -86,6 +88,7 @@ pub extern "C" fn rust_main() {
     let current = Context::k_current_get();
     current.k_object_access_grant::<Context, _>(&MUTEX);
     current.k_object_access_grant::<Context, _>(&TLS_SEM);
+    current.k_object_access_grant::<Context, _>(COUNTER.kmutex());
     mutex_test();
     std_mutex_test();
 
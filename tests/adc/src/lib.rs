@@ -0,0 +1,91 @@
+extern crate zephyr;
+extern crate zephyr_sys;
+
+use std::ffi::CStr;
+use std::os::raw::{c_int, c_uint};
+
+use futures::task::LocalSpawnExt;
+
+use zephyr::adc::{raw_to_millivolts, Adc, AdcChannelCfg, AdcGain, AdcReference, AdcSequence};
+use zephyr::context::Kernel as C;
+use zephyr::device::{Device, DeviceSyscalls};
+use zephyr_futures::{AdcExt, Executor};
+
+zephyr_macros::k_mutex_define!(EXECUTOR_MUTEX);
+zephyr_macros::k_poll_signal_define!(EXECUTOR_SIGNAL);
+zephyr_macros::k_poll_signal_define!(ADC_SIGNAL);
+
+extern "C" {
+    fn test_adc_emul_const_value_set(dev: *const Device, chan: c_uint, value: u32) -> c_int;
+}
+
+const REF_MV: i32 = 3300;
+const RESOLUTION: u8 = 12;
+
+fn value_set(adc: &Adc<C>, channel: u8, mv: u32) {
+    assert_eq!(
+        unsafe { test_adc_emul_const_value_set(adc.device(), channel as c_uint, mv) },
+        0
+    );
+}
+
+fn to_mv(raw: i16) -> i32 {
+    raw_to_millivolts(raw as i32, REF_MV, AdcGain::Gain1, RESOLUTION)
+}
+
+fn async_read(adc: &'static Adc<C>) {
+    let mut executor = unsafe { Executor::new(&EXECUTOR_MUTEX, &EXECUTOR_SIGNAL) };
+    executor
+        .spawn_local(async move {
+            value_set(adc, 0, 2475);
+            let mut buf = [0; 1];
+            let mut sequence = AdcSequence::new(&mut buf, RESOLUTION).channel(0);
+            // The future is awaited to completion here, never leaked
+            unsafe { adc.read_async(&mut sequence, &ADC_SIGNAL) }
+                .await
+                .expect("async read");
+            assert_eq!(to_mv(sequence.samples()[0]), 2475);
+        })
+        .unwrap();
+    executor.run::<C>();
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let adc: &'static Adc<C> = Box::leak(Box::new(unsafe {
+        let device = C::device_get_binding(CStr::from_bytes_with_nul_unchecked(
+            zephyr_sys::raw::DT_N_S_adc_P_label,
+        ))
+        .expect("get adc");
+        Adc::new(device)
+    }));
+    assert_eq!(adc.ref_internal() as i32, REF_MV);
+
+    for channel in 0..2 {
+        adc.channel_setup(&AdcChannelCfg::new(
+            channel,
+            AdcGain::Gain1,
+            AdcReference::Internal,
+        ))
+        .expect("channel setup");
+    }
+
+    value_set(adc, 0, 1650);
+    value_set(adc, 1, 825);
+    let mut buf = [0; 2];
+    let mut sequence = AdcSequence::new(&mut buf, RESOLUTION).channel(0).channel(1);
+    adc.read(&mut sequence).expect("read");
+    assert_eq!(sequence.samples(), &[2048, 1024]);
+    assert_eq!(to_mv(sequence.samples()[0]), 1650);
+    assert_eq!(to_mv(sequence.samples()[1]), 825);
+
+    // Averaging a constant input changes nothing
+    let mut sequence = AdcSequence::new(&mut buf, RESOLUTION)
+        .channel(1)
+        .oversampling(2);
+    adc.read(&mut sequence).expect("oversampled read");
+    assert_eq!(sequence.samples().len(), 1);
+    assert_eq!(to_mv(sequence.samples()[0]), 825);
+
+    async_read(adc);
+}
@@ -0,0 +1,16 @@
+#include <zephyr.h>
+#include <drivers/adc/adc_emul.h>
+
+extern void rust_test_main(void);
+
+/* The emulator API is not a syscall, so is not in the Rust bindings */
+int test_adc_emul_const_value_set(const struct device *dev, unsigned int chan,
+                                  uint32_t value)
+{
+    return adc_emul_const_value_set(dev, chan, value);
+}
+
+void test_main(void)
+{
+    rust_test_main();
+}
@@ -0,0 +1,56 @@
+extern crate libc;
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::time::Duration;
+
+use libc::c_void;
+use zephyr::condvar::Condvar;
+use zephyr::context::Kernel as C;
+
+zephyr_macros::static_mutex!(COUNT: u32 = 0);
+zephyr_macros::k_condvar_define!(TEST_CONDVAR);
+
+fn condvar() -> Condvar<'static> {
+    Condvar::new(TEST_CONDVAR.kobj())
+}
+
+#[no_mangle]
+pub extern "C" fn rust_condvar_thread(_a: *const c_void, _b: *const c_void, _c: *const c_void) {
+    for i in 1..=10 {
+        println!("Setting {}", i);
+        *COUNT.lock::<C>() = i;
+        condvar().notify_all::<C>();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let condvar = condvar();
+
+    // Wait for the other thread to count up, checking after each notification
+    let count = condvar.wait_while(COUNT.lock::<C>(), |count| *count < 10);
+    assert_eq!(*count, 10);
+
+    // Nobody left to notify. The mutex is held again after the timeout.
+    let (mut count, result) = condvar.wait_timeout(count, (&Duration::from_millis(10)).into());
+    assert!(result.timed_out());
+    *count = 0;
+    drop(count);
+    assert_eq!(*COUNT.try_lock::<C>().expect("try_lock"), 0);
+
+    // Notifying with no waiters is fine
+    condvar.notify_one::<C>();
+    condvar.notify_all::<C>();
+    assert_eq!(condvar.checked_notify_one::<C>(), Ok(()));
+    assert_eq!(condvar.checked_notify_all::<C>(), Ok(()));
+
+    // Already satisfied, so no wait
+    let count = condvar
+        .checked_wait_while(COUNT.lock::<C>(), |count| *count != 0)
+        .expect("checked_wait_while");
+    let (_count, result) = condvar
+        .checked_wait_timeout(count, zephyr::K_NO_WAIT)
+        .expect("checked_wait_timeout");
+    assert!(result.timed_out());
+}
@@ -0,0 +1,71 @@
+extern crate libc;
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use futures::task::LocalSpawnExt;
+
+use libc::c_void;
+use zephyr::context::Kernel as C;
+use zephyr::event::{Event, EventSignal};
+use zephyr_futures::{EventFuture, Executor};
+
+zephyr_macros::k_mutex_define!(EXECUTOR_MUTEX);
+zephyr_macros::k_poll_signal_define!(EXECUTOR_SIGNAL);
+zephyr_macros::k_poll_signal_define!(EVENT_SIGNAL);
+zephyr_macros::k_event_define!(TEST_EVENT);
+
+fn events() -> EventSignal<'static> {
+    EventSignal::new(TEST_EVENT.kobj(), EVENT_SIGNAL.kobj())
+}
+
+#[no_mangle]
+pub extern "C" fn rust_event_thread(_a: *const c_void, _b: *const c_void, _c: *const c_void) {
+    for i in 0..3 {
+        println!("Posting {}", i);
+        events().post::<C>(1 << i);
+    }
+}
+
+fn async_wait() {
+    let mut executor = unsafe { Executor::new(&EXECUTOR_MUTEX, &EXECUTOR_SIGNAL) };
+    executor
+        .spawn_local(async {
+            let matched = EventFuture::new(events(), 0b1_0000, false).await;
+            assert_eq!(matched, 0b1_0000);
+        })
+        .unwrap();
+    // Runs after the first task is waiting
+    executor
+        .spawn_local(async {
+            events().post::<C>(0b1_0000);
+        })
+        .unwrap();
+    executor.run::<C>();
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let event = TEST_EVENT.kobj();
+
+    // Wait for all three posts from the other thread
+    assert_eq!(
+        event.wait::<C>(0b111, true, false, zephyr::K_FOREVER),
+        Some(0b111)
+    );
+    assert_eq!(event.check::<C>(0b1, false), Some(0b1));
+    assert_eq!(event.check::<C>(0b1000, false), None);
+    assert_eq!(event.check::<C>(0b1001, true), None);
+
+    // Set replaces everything posted so far
+    events().set::<C>(0b1000);
+    assert_eq!(event.check::<C>(0b111, false), None);
+    assert_eq!(event.check::<C>(0b1001, false), Some(0b1000));
+
+    // Reset discards what was posted before waiting
+    assert_eq!(
+        event.wait::<C>(0b1000, false, true, zephyr::K_NO_WAIT),
+        None
+    );
+
+    async_wait();
+}
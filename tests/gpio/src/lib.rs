@@ -0,0 +1,139 @@
+extern crate zephyr;
+extern crate zephyr_sys;
+
+use std::ffi::CStr;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::task::LocalSpawnExt;
+
+use zephyr::context::Kernel as C;
+use zephyr::device::{Device, DeviceSyscalls};
+use zephyr::gpio::{gpio_pin_t, Edge, GpioFlags, GpioInterrupt, GpioPort, Pin};
+use zephyr_futures::delay::Delay;
+use zephyr_futures::{Executor, PinExt};
+
+zephyr_macros::k_mutex_define!(EXECUTOR_MUTEX);
+zephyr_macros::k_poll_signal_define!(EXECUTOR_SIGNAL);
+zephyr_macros::k_poll_signal_define!(EDGE_SIGNAL);
+
+extern "C" {
+    fn test_gpio_emul_input_set(port: *const Device, pin: gpio_pin_t, value: c_int) -> c_int;
+    fn test_gpio_emul_output_get(port: *const Device, pin: gpio_pin_t) -> c_int;
+}
+
+const OUT_PIN: gpio_pin_t = 0;
+const IN_PIN: gpio_pin_t = 1;
+
+fn input_set(port: GpioPort, pin: gpio_pin_t, high: bool) {
+    assert_eq!(
+        unsafe { test_gpio_emul_input_set(port.device(), pin, high as c_int) },
+        0
+    );
+}
+
+fn output_get(port: GpioPort, pin: gpio_pin_t) -> bool {
+    unsafe { test_gpio_emul_output_get(port.device(), pin) == 1 }
+}
+
+fn async_edges(port: GpioPort, mut input: Pin) {
+    let mut executor = unsafe { Executor::new(&EXECUTOR_MUTEX, &EXECUTOR_SIGNAL) };
+    let done = Arc::new(AtomicU32::new(0));
+
+    let waiter_done = done.clone();
+    executor
+        .spawn_local(async move {
+            input
+                .wait_for_edge(Edge::Falling, &EDGE_SIGNAL)
+                .await
+                .expect("wait for falling edge");
+            waiter_done.fetch_add(1, Ordering::SeqCst);
+            input
+                .wait_for_edge(Edge::Rising, &EDGE_SIGNAL)
+                .await
+                .expect("wait for rising edge");
+            waiter_done.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+    let driver_done = done.clone();
+    executor
+        .spawn_local(async move {
+            // Starts high from the synchronous test
+            Delay::new(Duration::from_millis(10)).await;
+            input_set(port, IN_PIN, false);
+            Delay::new(Duration::from_millis(10)).await;
+            assert_eq!(driver_done.load(Ordering::SeqCst), 1);
+            input_set(port, IN_PIN, true);
+        })
+        .unwrap();
+
+    executor.run::<C>();
+    assert_eq!(done.load(Ordering::SeqCst), 2);
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let port = unsafe {
+        let device = C::device_get_binding(CStr::from_bytes_with_nul_unchecked(
+            zephyr_sys::raw::DT_N_S_test_gpio_P_label,
+        ))
+        .expect("get gpio");
+        GpioPort::new(device)
+    };
+
+    // Active low output: logical and physical levels are inverted
+    let out = port
+        .pin(OUT_PIN)
+        .configure::<C>(GpioFlags::OUTPUT_INACTIVE | GpioFlags::ACTIVE_LOW)
+        .expect("configure output");
+    assert!(output_get(port, OUT_PIN));
+    out.set::<C>(true).expect("set");
+    assert!(!output_get(port, OUT_PIN));
+    out.toggle::<C>().expect("toggle");
+    assert!(output_get(port, OUT_PIN));
+    out.set_raw::<C>(false).expect("set raw");
+    assert!(!output_get(port, OUT_PIN));
+    port.set_masked_raw::<C>(out.mask(), out.mask())
+        .expect("set masked");
+    assert!(output_get(port, OUT_PIN));
+
+    let input = port
+        .pin(IN_PIN)
+        .configure::<C>(GpioFlags::INPUT)
+        .expect("configure input");
+    input_set(port, IN_PIN, true);
+    assert_eq!(input.get::<C>().ok(), Some(true));
+    assert_eq!(port.get_raw::<C>().unwrap() & input.mask(), input.mask());
+    input_set(port, IN_PIN, false);
+    assert_eq!(input.get_raw::<C>().ok(), Some(false));
+
+    // Callbacks run on each rising edge until dropped
+    let edges = Arc::new(AtomicU32::new(0));
+    let callback = {
+        let edges = edges.clone();
+        input.add_callback::<C, _>(move || {
+            edges.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+    .expect("add callback");
+    input
+        .interrupt_configure::<C>(GpioInterrupt::EDGE_RISING)
+        .expect("interrupt configure");
+    input_set(port, IN_PIN, true);
+    input_set(port, IN_PIN, false);
+    input_set(port, IN_PIN, true);
+    assert_eq!(edges.load(Ordering::SeqCst), 2);
+
+    drop(callback);
+    input_set(port, IN_PIN, false);
+    input_set(port, IN_PIN, true);
+    assert_eq!(edges.load(Ordering::SeqCst), 2);
+    input
+        .interrupt_configure::<C>(GpioInterrupt::DISABLE)
+        .expect("interrupt disable");
+
+    async_edges(port, input);
+}
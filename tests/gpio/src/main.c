@@ -0,0 +1,21 @@
+#include <zephyr.h>
+#include <drivers/gpio/gpio_emul.h>
+
+extern void rust_test_main(void);
+
+/* The emulator API is not a syscall, so is not in the Rust bindings */
+int test_gpio_emul_input_set(const struct device *port, gpio_pin_t pin,
+                             int value)
+{
+    return gpio_emul_input_set(port, pin, value);
+}
+
+int test_gpio_emul_output_get(const struct device *port, gpio_pin_t pin)
+{
+    return gpio_emul_output_get(port, pin);
+}
+
+void test_main(void)
+{
+    rust_test_main();
+}
@@ -0,0 +1,117 @@
+#![feature(allocator_api)]
+
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::alloc::{Allocator, Layout};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+zephyr_macros::k_heap_define!(TEST_HEAP, 1024);
+zephyr_macros::k_appmem_partition_define!(heap_partition);
+zephyr_macros::k_heap_define!(PARTITION_HEAP, 512, partition = heap_partition);
+
+static FAILED_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+fn record_failure(layout: Layout) {
+    FAILED_SIZE.store(layout.size(), Ordering::SeqCst);
+}
+
+#[repr(align(16))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Align16(u8);
+
+#[repr(align(32))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Align32(u8);
+
+#[repr(align(64))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Align64(u8);
+
+fn is_aligned<T>(ptr: *const T) -> bool {
+    ptr as usize % std::mem::align_of::<T>() == 0
+}
+
+fn check_aligned<T: Copy + Debug + PartialEq>(value: T) {
+    let b = Box::new(value);
+    assert!(is_aligned(&*b));
+
+    // Growing and shrinking keep the alignment and contents
+    let mut v = Vec::with_capacity(1);
+    for _ in 0..8 {
+        v.push(value);
+        assert!(is_aligned(v.as_ptr()));
+    }
+    v.truncate(2);
+    v.shrink_to_fit();
+    assert!(is_aligned(v.as_ptr()));
+    assert_eq!(v, [value, value]);
+
+    unsafe {
+        let layout = Layout::new::<[T; 4]>();
+        let ptr = std::alloc::alloc_zeroed(layout);
+        assert!(!ptr.is_null());
+        assert!(is_aligned(ptr as *const T));
+        assert!(std::slice::from_raw_parts(ptr, layout.size())
+            .iter()
+            .all(|&b| b == 0));
+        std::alloc::dealloc(ptr, layout);
+    }
+
+    let b = Box::new_in(value, &TEST_HEAP);
+    assert!(is_aligned(&*b));
+    let mut v = Vec::new_in(&TEST_HEAP);
+    v.extend([value; 4].iter().copied());
+    assert!(is_aligned(v.as_ptr()));
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let b = Box::new_in(0x1234_5678u32, &TEST_HEAP);
+    assert_eq!(*b, 0x1234_5678);
+
+    let mut v = Vec::new_in(&TEST_HEAP);
+    v.extend(0..64u32);
+    assert_eq!(v.iter().sum::<u32>(), 2016);
+
+    // More than the whole heap
+    assert!(TEST_HEAP
+        .allocate(Layout::from_size_align(2048, 4).unwrap())
+        .is_err());
+
+    // Memory is returned on drop
+    drop(b);
+    drop(v);
+    for _ in 0..16 {
+        let v: Vec<u8, _> = Vec::with_capacity_in(512, &TEST_HEAP);
+        assert!(v.capacity() >= 512);
+    }
+
+    // A heap in an app memory partition works the same from kernel mode
+    let mut v = Vec::new_in(&PARTITION_HEAP);
+    v.extend(0..16u32);
+    assert_eq!(v.iter().sum::<u32>(), 120);
+    assert!(PARTITION_HEAP
+        .allocate(Layout::from_size_align(1024, 4).unwrap())
+        .is_err());
+    drop(v);
+    let b = Box::new_in([0u8; 128], &PARTITION_HEAP);
+    assert!(b.iter().all(|&b| b == 0));
+    drop(b);
+
+    // Zero sized allocations do not touch the heap
+    let zst = Box::new_in((), &TEST_HEAP);
+    assert_eq!(*zst, ());
+
+    check_aligned(Align16(16));
+    check_aligned(Align32(32));
+    check_aligned(Align64(64));
+
+    // Global allocation failures are reported to the hook
+    zephyr::mempool::set_alloc_error_hook(record_failure);
+    let mut big: Vec<u8> = Vec::new();
+    assert!(big.try_reserve_exact(1 << 20).is_err());
+    assert_eq!(FAILED_SIZE.load(Ordering::SeqCst), 1 << 20);
+    zephyr::mempool::set_alloc_error_hook(zephyr::mempool::default_alloc_error_hook);
+}
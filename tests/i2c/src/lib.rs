@@ -0,0 +1,56 @@
+extern crate zephyr;
+extern crate zephyr_sys;
+
+use std::ffi::CStr;
+
+use embedded_hal::i2c::{I2c as _, Operation};
+use zephyr::context::Any as C;
+use zephyr::device::DeviceSyscalls;
+use zephyr::i2c::{I2c, I2cMsg, I2cSpeed};
+
+/// AT24 EEPROM emulator on the emulated controller. Writes start with a one byte memory address.
+const EEPROM_ADDR: u16 = 0x54;
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let mut i2c = unsafe {
+        let device = C::device_get_binding(CStr::from_bytes_with_nul_unchecked(
+            zephyr_sys::raw::DT_N_S_i2c_100_P_label,
+        ))
+        .expect("get i2c");
+        I2c::<C>::new(device)
+    };
+    i2c.configure(I2cSpeed::Standard).expect("configure");
+
+    let mut read = [0; 4];
+    i2c.write(EEPROM_ADDR, &[0x10, 1, 2, 3, 4]).expect("write");
+    i2c.write_read(EEPROM_ADDR, &[0x10], &mut read)
+        .expect("write_read");
+    assert_eq!(read, [1, 2, 3, 4]);
+
+    // Explicit messages, reading from the second byte
+    let mut read = [0; 2];
+    i2c.transfer(
+        &mut [
+            I2cMsg::write(&[0x11]),
+            I2cMsg::read(&mut read).restart().stop(),
+        ],
+        EEPROM_ADDR,
+    )
+    .expect("transfer");
+    assert_eq!(read, [2, 3]);
+
+    // Nothing at this address
+    assert!(i2c.write(0x55, &[0x10, 0]).is_err());
+
+    // Through the embedded-hal trait, as a sensor driver crate would
+    embedded_hal::i2c::I2c::write(&mut i2c, EEPROM_ADDR as u8, &[0x20, 5, 6]).expect("hal write");
+    let mut read = [0; 2];
+    i2c.transaction(
+        EEPROM_ADDR as u8,
+        &mut [Operation::Write(&[0x20]), Operation::Read(&mut read)],
+    )
+    .expect("hal transaction");
+    assert_eq!(read, [5, 6]);
+    assert!(embedded_hal::i2c::I2c::read(&mut i2c, 0x55u8, &mut read).is_err());
+}
@@ -0,0 +1,8 @@
+#include <zephyr.h>
+
+extern void rust_test_main(void);
+
+void test_main(void)
+{
+    rust_test_main();
+}
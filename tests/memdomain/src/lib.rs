@@ -0,0 +1,52 @@
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use zephyr::context::Kernel as C;
+use zephyr::memdomain::MemDomain;
+use zephyr::thread::ThreadSyscalls;
+use zephyr::Error;
+use zephyr_macros::{app_bmem, app_mem};
+
+zephyr_macros::k_appmem_partition_define!(test_partition);
+zephyr_macros::k_mem_domain_define!(TEST_DOMAIN);
+
+#[app_mem(test_partition)]
+static mut PRIVATE_DATA: u32 = 0x1234_5678;
+
+#[app_bmem(test_partition)]
+static mut PRIVATE_BSS: [u8; 16] = [0; 16];
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    // Statics in the partition are initialized like any other, and its bss is zeroed at boot
+    unsafe {
+        assert_eq!(PRIVATE_DATA, 0x1234_5678);
+        assert_eq!(PRIVATE_BSS, [0; 16]);
+        PRIVATE_BSS[0] = 1;
+    }
+
+    assert!(TEST_DOMAIN.get().is_none());
+    let std_partition = zephyr::static_mem_partition!(rust_std_partition);
+    let domain = TEST_DOMAIN
+        .init::<C>(&[&test_partition, std_partition])
+        .expect("domain init");
+    assert!(TEST_DOMAIN.get().is_some());
+    assert_eq!(TEST_DOMAIN.init::<C>(&[]).err(), Some(Error::Already));
+
+    // Partitions can be swapped at runtime
+    domain
+        .remove_partition::<C>(&test_partition)
+        .expect("remove partition");
+    domain
+        .add_partition::<C>(&test_partition)
+        .expect("add partition");
+
+    // Move this thread in and back out
+    let current = C::k_current_get();
+    domain.add_thread::<C>(current).expect("add thread");
+    unsafe { assert_eq!(PRIVATE_BSS[0], 1) };
+    MemDomain::remove_thread::<C>(current).expect("remove thread");
+    zephyr::static_mem_domain!(rust_std_domain)
+        .add_thread::<C>(current)
+        .expect("add thread to rust_std_domain");
+}
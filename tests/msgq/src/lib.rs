@@ -0,0 +1,62 @@
+extern crate libc;
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::time::Duration;
+
+use libc::c_void;
+use zephyr::context::Kernel as C;
+use zephyr::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Record {
+    seq: u32,
+    value: u16,
+}
+
+zephyr_macros::k_msgq_define!(TEST_MSGQ, Record, 4);
+
+fn record(i: u32) -> Record {
+    Record {
+        seq: i,
+        value: (i * 2) as u16,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_msgq_thread(_a: *const c_void, _b: *const c_void, _c: *const c_void) {
+    for i in 0..10 {
+        println!("Putting {}", i);
+        TEST_MSGQ.put::<C>(&record(i)).expect("put");
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    for i in 0..10 {
+        println!("Getting {}", i);
+        assert_eq!(TEST_MSGQ.get::<C>(), Ok(record(i)));
+        println!("Got {}", i);
+    }
+
+    // Empty
+    assert_eq!(TEST_MSGQ.try_get::<C>(), None);
+    assert_eq!(TEST_MSGQ.peek::<C>(), None);
+    assert_eq!(
+        TEST_MSGQ.get_timeout::<C>((&Duration::from_millis(10)).into()),
+        Err(Error::Again)
+    );
+
+    // Full
+    for i in 0..4 {
+        TEST_MSGQ.try_put::<C>(&record(i)).expect("try_put");
+    }
+    assert_eq!(TEST_MSGQ.num_used::<C>(), 4);
+    assert_eq!(TEST_MSGQ.num_free::<C>(), 0);
+    assert_eq!(TEST_MSGQ.try_put::<C>(&record(4)), Err(Error::NoMsg));
+    assert_eq!(TEST_MSGQ.peek::<C>(), Some(record(0)));
+
+    TEST_MSGQ.purge::<C>();
+    assert_eq!(TEST_MSGQ.num_used::<C>(), 0);
+    assert_eq!(TEST_MSGQ.num_free::<C>(), 4);
+}
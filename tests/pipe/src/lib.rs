@@ -0,0 +1,46 @@
+extern crate libc;
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::io::{ErrorKind, Read, Write};
+
+use libc::c_void;
+use zephyr::context::Kernel as C;
+use zephyr::pipe::Pipe;
+
+zephyr_macros::k_pipe_define!(TEST_PIPE, 16);
+
+#[no_mangle]
+pub extern "C" fn rust_pipe_thread(_a: *const c_void, _b: *const c_void, _c: *const c_void) {
+    let mut pipe = Pipe::<C>::new(TEST_PIPE.kobj());
+    // Larger than the pipe buffer, so this blocks until the reader catches up
+    pipe.write_all(&[0x5a; 64]).expect("write_all");
+    println!("Wrote 64 bytes");
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let mut pipe = Pipe::<C>::new(TEST_PIPE.kobj());
+    let mut buf = [0u8; 64];
+    pipe.read_exact(&mut buf).expect("read_exact");
+    assert!(buf.iter().all(|&b| b == 0x5a));
+
+    // Empty pipe, no waiting
+    pipe.set_timeout(zephyr::K_NO_WAIT);
+    pipe.set_min_xfer(0);
+    assert_eq!(
+        pipe.read(&mut buf).map_err(|e| e.kind()),
+        Err(ErrorKind::WouldBlock)
+    );
+
+    // Partial write into a full buffer returns the partial count
+    assert_eq!(pipe.write(&[1; 20]).expect("write"), 16);
+    assert_eq!(
+        pipe.write(&[1; 1]).map_err(|e| e.kind()),
+        Err(ErrorKind::WouldBlock)
+    );
+
+    // Drain what was written, even though it is less than the buffer
+    assert_eq!(pipe.read(&mut buf).expect("read"), 16);
+    assert!(buf[..16].iter().all(|&b| b == 1));
+}
@@ -0,0 +1,83 @@
+extern crate libc;
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::time::Duration;
+
+use libc::c_void;
+use zephyr::context::Kernel as C;
+use zephyr::queue::{Fifo, Lifo, Queue};
+
+#[derive(Debug, PartialEq)]
+struct Record {
+    seq: u32,
+    value: u16,
+}
+
+zephyr_macros::k_queue_define!(TEST_FIFO);
+zephyr_macros::k_queue_define!(TEST_LIFO);
+
+fn record(i: u32) -> Box<Record> {
+    Box::new(Record {
+        seq: i,
+        value: (i * 2) as u16,
+    })
+}
+
+fn fifo() -> Fifo<'static, Record> {
+    unsafe { Fifo::new(TEST_FIFO.kobj()) }
+}
+
+fn lifo() -> Lifo<'static, Record> {
+    unsafe { Lifo::new(TEST_LIFO.kobj()) }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_queue_thread(_a: *const c_void, _b: *const c_void, _c: *const c_void) {
+    for i in 0..10 {
+        println!("Pushing {}", i);
+        fifo().push::<C>(record(i));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let fifo = fifo();
+    for i in 0..10 {
+        println!("Popping {}", i);
+        assert_eq!(fifo.pop::<C>(zephyr::K_FOREVER), Some(record(i)));
+        println!("Popped {}", i);
+    }
+
+    // Empty
+    assert!(fifo.is_empty::<C>());
+    assert_eq!(fifo.try_pop::<C>(), None);
+    assert_eq!(fifo.pop::<C>((&Duration::from_millis(10)).into()), None);
+
+    // Kernel-allocated list entries
+    fifo.push_alloc::<C>(record(10)).expect("push_alloc");
+    fifo.push_alloc::<C>(record(11)).expect("push_alloc");
+    assert!(!fifo.is_empty::<C>());
+    assert_eq!(fifo.try_pop::<C>(), Some(record(10)));
+    assert_eq!(fifo.try_pop::<C>(), Some(record(11)));
+
+    // Last in, first out, mixing both kinds of insertion
+    let lifo = lifo();
+    lifo.push::<C>(record(0));
+    lifo.push_alloc::<C>(record(1)).expect("push_alloc");
+    lifo.push::<C>(record(2));
+    for i in (0..3).rev() {
+        assert_eq!(lifo.try_pop::<C>(), Some(record(i)));
+    }
+    assert!(lifo.is_empty::<C>());
+
+    // The untyped order operations on the same queue
+    let queue: Queue<Record> = lifo.queue();
+    queue.append::<C>(record(1));
+    queue.prepend::<C>(record(0));
+    queue.alloc_append::<C>(record(2)).expect("alloc_append");
+    for i in 0..3 {
+        assert_eq!(queue.try_get::<C>(), Some(record(i)));
+    }
+    assert!(queue.is_empty::<C>());
+}
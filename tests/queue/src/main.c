@@ -0,0 +1,12 @@
+#include <zephyr.h>
+
+extern void rust_test_main(void);
+extern void rust_queue_thread(void *, void *, void *);
+
+K_THREAD_DEFINE(queue_thread, 1024, rust_queue_thread, NULL, NULL, NULL,
+                K_LOWEST_APPLICATION_THREAD_PRIO, 0, 0);
+
+void test_main(void)
+{
+    rust_test_main();
+}
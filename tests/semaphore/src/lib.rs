@@ -23,4 +23,13 @@ pub extern "C" fn rust_test_main() {
         TEST_SEM.take::<C>();
         println!("Took {}", i);
     }
+
+    // Empty, so neither waits nor panics
+    assert_eq!(TEST_SEM.checked_try_take::<C>(), Ok(false));
+    assert_eq!(
+        TEST_SEM.checked_take_timeout::<C>(zephyr::K_NO_WAIT),
+        Ok(false)
+    );
+    TEST_SEM.give::<C>();
+    assert_eq!(TEST_SEM.checked_take::<C>(), Ok(()));
 }
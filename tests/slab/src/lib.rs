@@ -0,0 +1,85 @@
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use zephyr::context::Kernel as C;
+use zephyr::Error;
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq)]
+struct Packet {
+    seq: u32,
+    payload: [u8; 13],
+}
+
+impl Drop for Packet {
+    fn drop(&mut self) {
+        DROPS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[repr(align(32))]
+struct Aligned(u8);
+
+zephyr_macros::k_mem_slab_define!(PACKETS, Packet, 3);
+zephyr_macros::k_mem_slab_define!(ALIGNED, Aligned, 2);
+
+fn packet(seq: u32) -> Packet {
+    Packet {
+        seq,
+        payload: [seq as u8; 13],
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    assert_eq!(PACKETS.num_free::<C>(), 3);
+    assert_eq!(PACKETS.num_used::<C>(), 0);
+
+    let mut a = PACKETS.alloc::<C>(packet(1));
+    let b = PACKETS.try_alloc::<C>(packet(2)).unwrap();
+    let c = PACKETS
+        .alloc_timeout::<C>(packet(3), (&Duration::from_millis(10)).into())
+        .unwrap();
+    assert_eq!(PACKETS.num_used::<C>(), 3);
+    assert_eq!(PACKETS.num_free::<C>(), 0);
+    assert_eq!(a.seq, 1);
+    assert_eq!(b.payload, [2; 13]);
+    a.seq = 10;
+    assert_eq!(*a, packet(10));
+
+    // Full. The rejected values are dropped.
+    let drops = DROPS.load(Ordering::SeqCst);
+    assert_eq!(
+        PACKETS.try_alloc::<C>(packet(4)).unwrap_err(),
+        Error::NoMemory
+    );
+    assert_eq!(
+        PACKETS
+            .alloc_timeout::<C>(packet(5), (&Duration::from_millis(10)).into())
+            .unwrap_err(),
+        Error::Again
+    );
+    assert_eq!(DROPS.load(Ordering::SeqCst), drops + 2);
+
+    // Blocks are returned on drop, after dropping the value
+    let drops = DROPS.load(Ordering::SeqCst);
+    drop(b);
+    assert_eq!(DROPS.load(Ordering::SeqCst), drops + 1);
+    assert_eq!(PACKETS.num_free::<C>(), 1);
+
+    let seq = zephyr::slab::SlabBox::into_inner(c).seq;
+    assert_eq!(seq, 3);
+    assert_eq!(PACKETS.num_free::<C>(), 2);
+    drop(a);
+    assert_eq!(PACKETS.num_free::<C>(), 3);
+
+    let x = ALIGNED.alloc::<C>(Aligned(1));
+    let y = ALIGNED.alloc::<C>(Aligned(2));
+    assert_eq!(&*x as *const Aligned as usize % 32, 0);
+    assert_eq!(&*y as *const Aligned as usize % 32, 0);
+    assert_eq!(x.0 + y.0, 3);
+}
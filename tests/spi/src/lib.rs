@@ -0,0 +1,106 @@
+extern crate zephyr;
+extern crate zephyr_sys;
+
+use std::ffi::CStr;
+
+use embedded_hal::spi::{Operation, SpiDevice};
+
+use zephyr::context::Any as C;
+use zephyr::device::{Device, DeviceSyscalls};
+use zephyr::gpio::{GpioFlags, GpioPort};
+use zephyr::spi::{Spi, SpiConfig};
+
+/// BMI160 emulator on chip select 3 of the emulated controller. The first byte selects the
+/// register, with the top bit set for a read.
+const BMI160_SLAVE: u16 = 3;
+const BMI160_REG_READ: u8 = 0x80;
+const BMI160_REG_CHIPID: u8 = 0x00;
+const BMI160_CHIP_ID: u8 = 0xd1;
+const BMI160_REG_ACC_CONF: u8 = 0x40;
+
+fn read_reg(spi: &Spi<C>, reg: u8) -> u8 {
+    let mut val = [0];
+    spi.transceive(&[&[reg | BMI160_REG_READ], &[0]], &mut [&mut [0], &mut val])
+        .expect("read reg");
+    val[0]
+}
+
+fn get_device(label: &[u8]) -> &'static Device {
+    unsafe { C::device_get_binding(CStr::from_bytes_with_nul_unchecked(label)) }
+        .expect("get device")
+}
+
+/// Register access through embedded-hal, as a sensor driver crate would do it
+fn transactions(mut spi: Spi<C>) {
+    let mut id = [0];
+    spi.transaction(&mut [
+        Operation::Write(&[BMI160_REG_CHIPID | BMI160_REG_READ]),
+        Operation::Read(&mut id),
+    ])
+    .expect("read transaction");
+    assert_eq!(id[0], BMI160_CHIP_ID);
+
+    spi.transaction(&mut [
+        Operation::Write(&[BMI160_REG_ACC_CONF]),
+        Operation::Write(&[0x2a]),
+    ])
+    .expect("write transaction");
+
+    // The byte clocked out is overwritten with the register value
+    let mut conf = [0xff];
+    spi.transaction(&mut [
+        Operation::Write(&[BMI160_REG_ACC_CONF | BMI160_REG_READ]),
+        Operation::TransferInPlace(&mut conf),
+    ])
+    .expect("transfer in place");
+    assert_eq!(conf[0], 0x2a);
+
+    let mut conf = [0];
+    spi.transaction(&mut [
+        Operation::Transfer(&mut [], &[BMI160_REG_ACC_CONF | BMI160_REG_READ]),
+        Operation::Transfer(&mut conf, &[]),
+    ])
+    .expect("transfer");
+    assert_eq!(conf[0], 0x2a);
+
+    // Only a delay. The bus is still released afterward.
+    spi.transaction(&mut [Operation::DelayNs(1_500)])
+        .expect("delay");
+    assert_eq!(read_reg(&spi, BMI160_REG_CHIPID), BMI160_CHIP_ID);
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let device = get_device(zephyr_sys::raw::DT_N_S_spi_200_P_label);
+    let spi = unsafe {
+        Spi::<C>::new(
+            device,
+            SpiConfig::builder(1_000_000)
+                .mode(embedded_hal::spi::MODE_0)
+                .slave(BMI160_SLAVE)
+                .build(),
+        )
+    };
+    assert_eq!(spi.config().frequency(), 1_000_000);
+
+    assert_eq!(read_reg(&spi, BMI160_REG_CHIPID), BMI160_CHIP_ID);
+
+    spi.write(&[&[BMI160_REG_ACC_CONF], &[0x28]])
+        .expect("write reg");
+    assert_eq!(read_reg(&spi, BMI160_REG_ACC_CONF), 0x28);
+
+    spi.release().expect("release");
+
+    let cs = unsafe { GpioPort::new(get_device(zephyr_sys::raw::DT_N_S_spi_cs_P_label)) };
+    let spi = unsafe {
+        Spi::<C>::new(
+            device,
+            SpiConfig::builder(1_000_000)
+                .mode(embedded_hal::spi::MODE_0)
+                .slave(BMI160_SLAVE)
+                .cs_gpio(cs.pin(0), GpioFlags::ACTIVE_LOW)
+                .build(),
+        )
+    };
+    transactions(spi);
+}
@@ -0,0 +1,128 @@
+extern crate zephyr;
+extern crate zephyr_sys;
+
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+use zephyr::context::Kernel as C;
+use zephyr::thread::{
+    Builder, JoinError, KThread, KThreadStack, ThreadOptions, HEAP_STACK_SUPPORTED,
+};
+use zephyr::Error;
+
+extern "C" {
+    static rust_thread: KThread;
+    static mut rust_thread_stack: KThreadStack;
+    static rust_thread_stack_size: usize;
+}
+
+static STOP: AtomicBool = AtomicBool::new(false);
+static TICKS: AtomicU32 = AtomicU32::new(0);
+
+/// A thread object and stack defined in C. Only one thread may use them at a time.
+fn static_builder() -> Builder {
+    unsafe {
+        Builder::new()
+            .stack_static(
+                core::ptr::addr_of_mut!(rust_thread_stack),
+                rust_thread_stack_size,
+            )
+            .thread_static(&rust_thread)
+    }
+}
+
+fn static_threads() {
+    let name = CStr::from_bytes_with_nul(b"static\0").unwrap();
+    let handle = static_builder()
+        .name(name)
+        .spawn::<C, _, _>(|| (1..=10).sum::<u32>())
+        .expect("spawn static");
+    let actual =
+        unsafe { CStr::from_ptr(zephyr_sys::raw::k_thread_name_get(handle.thread().tid())) };
+    assert_eq!(actual, name);
+    assert_eq!(handle.join(), Ok(55));
+
+    // Suspended threads make no progress until resumed
+    STOP.store(false, Ordering::SeqCst);
+    let handle = static_builder()
+        .spawn::<C, _, _>(|| {
+            while !STOP.load(Ordering::SeqCst) {
+                TICKS.fetch_add(1, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        })
+        .expect("spawn static");
+    std::thread::sleep(Duration::from_millis(10));
+    handle.suspend();
+    let ticks = TICKS.load(Ordering::SeqCst);
+    assert!(ticks > 0);
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(TICKS.load(Ordering::SeqCst), ticks);
+    handle.resume();
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(TICKS.load(Ordering::SeqCst) > ticks);
+    STOP.store(true, Ordering::SeqCst);
+    assert_eq!(handle.join(), Ok(()));
+
+    // Pinned to CPU 0, which every platform has
+    let handle = static_builder()
+        .cpu_mask(1)
+        .spawn::<C, _, _>(|| 7)
+        .expect("spawn with cpu mask");
+    assert_eq!(handle.join(), Ok(7));
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    static_threads();
+
+    // User threads need a thread object and stack known to the kernel
+    assert_eq!(
+        Builder::new()
+            .options(ThreadOptions::USER)
+            .spawn::<C, _, _>(|| ())
+            .err(),
+        Some(Error::NotSupported)
+    );
+
+    if !HEAP_STACK_SUPPORTED {
+        assert_eq!(
+            Builder::new().spawn::<C, _, _>(|| ()).err(),
+            Some(Error::NotSupported)
+        );
+        return;
+    }
+
+    let handle = Builder::new()
+        .name(CStr::from_bytes_with_nul(b"adder\0").unwrap())
+        .stack_size(2048)
+        .spawn::<C, _, _>(|| (1..=10).sum::<u32>())
+        .expect("spawn");
+    assert_eq!(handle.join(), Ok(55));
+
+    // A thread that does not exit in time
+    STOP.store(false, Ordering::SeqCst);
+    let mut handle = Builder::new()
+        .spawn::<C, _, _>(|| {
+            while !STOP.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        })
+        .expect("spawn");
+    assert_eq!(
+        handle.join_timeout((&Duration::from_millis(10)).into()),
+        Err(JoinError::TimedOut)
+    );
+    STOP.store(true, Ordering::SeqCst);
+    assert_eq!(handle.join_timeout(zephyr::K_FOREVER), Ok(()));
+
+    // Aborted threads produce no result
+    let handle = Builder::new()
+        .spawn::<C, _, _>(|| loop {
+            std::thread::sleep(Duration::from_millis(1));
+        })
+        .expect("spawn");
+    handle.abort();
+    assert_eq!(handle.join(), Err(JoinError::Aborted));
+}
@@ -0,0 +1,13 @@
+#include <zephyr.h>
+
+extern void rust_test_main(void);
+
+/* Usable on every platform, unlike a heap allocated stack */
+K_THREAD_STACK_DEFINE(rust_thread_stack, 1024);
+const size_t rust_thread_stack_size = K_THREAD_STACK_SIZEOF(rust_thread_stack);
+struct k_thread rust_thread;
+
+void test_main(void)
+{
+    rust_test_main();
+}
@@ -0,0 +1,72 @@
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use zephyr::context::Kernel as C;
+use zephyr::thread::ThreadId;
+
+extern "C" {
+    fn test_is_user_context() -> bool;
+    static test_userspace: bool;
+}
+
+static KERNEL_RAN: AtomicBool = AtomicBool::new(false);
+static KERNEL_IN_USER: AtomicBool = AtomicBool::new(true);
+static USER_RAN: AtomicBool = AtomicBool::new(false);
+static USER_IN_USER: AtomicBool = AtomicBool::new(false);
+
+zephyr_macros::k_thread_define!(
+    KERNEL_THREAD,
+    kernel_thread,
+    stack = 1024,
+    priority = 5,
+    delay = -1
+);
+zephyr_macros::k_thread_define!(
+    USER_THREAD,
+    user_thread,
+    stack = 1024,
+    priority = 5,
+    delay = -1,
+    user
+);
+
+fn kernel_thread() {
+    KERNEL_IN_USER.store(unsafe { test_is_user_context() }, Ordering::SeqCst);
+    KERNEL_RAN.store(true, Ordering::SeqCst);
+}
+
+fn user_thread() {
+    USER_IN_USER.store(unsafe { test_is_user_context() }, Ordering::SeqCst);
+    USER_RAN.store(true, Ordering::SeqCst);
+}
+
+/// Start a thread created with a delay of -1 and wait for it to run
+fn start_and_wait(thread: &ThreadId, ran: &AtomicBool) {
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(
+        !ran.load(Ordering::SeqCst),
+        "thread ran before being started"
+    );
+    thread.start::<C>();
+    for _ in 0..100 {
+        if ran.load(Ordering::SeqCst) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("thread did not run after being started");
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    start_and_wait(&KERNEL_THREAD, &KERNEL_RAN);
+    assert!(!KERNEL_IN_USER.load(Ordering::SeqCst));
+
+    start_and_wait(&USER_THREAD, &USER_RAN);
+    assert_eq!(USER_IN_USER.load(Ordering::SeqCst), unsafe {
+        test_userspace
+    });
+}
@@ -0,0 +1,16 @@
+#include <zephyr.h>
+
+extern void rust_test_main(void);
+
+/* k_is_user_context is inline, so not callable from Rust */
+bool test_is_user_context(void)
+{
+    return k_is_user_context();
+}
+
+const bool test_userspace = IS_ENABLED(CONFIG_USERSPACE);
+
+void test_main(void)
+{
+    rust_test_main();
+}
@@ -0,0 +1,128 @@
+extern crate zephyr;
+extern crate zephyr_macros;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::StreamExt;
+use futures::task::LocalSpawnExt;
+
+use zephyr::context::Kernel as C;
+use zephyr::timer::{CallbackTimer, Timer, TimerIsr};
+use zephyr::Timeout;
+use zephyr_futures::{Executor, TimerStream};
+
+zephyr_macros::k_mutex_define!(EXECUTOR_MUTEX);
+zephyr_macros::k_poll_signal_define!(EXECUTOR_SIGNAL);
+zephyr_macros::k_poll_signal_define!(TIMER_SIGNAL);
+zephyr_macros::k_timer_define!(TEST_TIMER);
+
+fn ms(ms: u64) -> Timeout {
+    (&Duration::from_millis(ms)).into()
+}
+
+/// Sleep until `count` reaches `min`, failing after a second
+fn wait_for(count: &AtomicU32, min: u32) {
+    for _ in 0..1000 {
+        if count.load(Ordering::SeqCst) >= min {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("count stuck at {}", count.load(Ordering::SeqCst));
+}
+
+fn plain_timer() {
+    let timer = Timer::new(TEST_TIMER.kobj());
+    timer.start::<C>(ms(10), zephyr::K_NO_WAIT);
+    assert!(timer.remaining_get::<C>() > Duration::from_millis(0));
+    assert_eq!(timer.status_sync::<C>(), 1);
+    assert_eq!(timer.status_get::<C>(), 0);
+    assert_eq!(timer.remaining_get::<C>(), Duration::from_millis(0));
+}
+
+fn callback_timer() {
+    let expired = Arc::new(AtomicU32::new(0));
+    let stopped = Arc::new(AtomicU32::new(0));
+    let timer = {
+        let expired = expired.clone();
+        let stopped = stopped.clone();
+        unsafe {
+            CallbackTimer::with_stop(
+                TEST_TIMER.kobj(),
+                move |_: &TimerIsr| {
+                    expired.fetch_add(1, Ordering::SeqCst);
+                },
+                move |_: &TimerIsr| {
+                    stopped.fetch_add(1, Ordering::SeqCst);
+                },
+            )
+        }
+    };
+
+    timer.start::<C>(ms(1), ms(1));
+    wait_for(&expired, 5);
+    // Stopping a running timer calls the stop handler in this thread
+    timer.stop::<C>();
+    assert_eq!(stopped.load(Ordering::SeqCst), 1);
+    let count = expired.load(Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(expired.load(Ordering::SeqCst), count);
+
+    // Drop while the timer is still firing. The handlers, and their references to the counters,
+    // are freed and never called again.
+    timer.start::<C>(ms(1), ms(1));
+    wait_for(&expired, count + 5);
+    drop(timer);
+    assert_eq!(Arc::strong_count(&expired), 1);
+    assert_eq!(Arc::strong_count(&stopped), 1);
+    let count = expired.load(Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(expired.load(Ordering::SeqCst), count);
+}
+
+fn restart_from_handler() {
+    let expired = Arc::new(AtomicU32::new(0));
+    let timer = {
+        let expired = expired.clone();
+        unsafe {
+            CallbackTimer::new(TEST_TIMER.kobj(), move |timer: &TimerIsr| {
+                // One-shot timer rescheduling itself a few times
+                if expired.fetch_add(1, Ordering::SeqCst) < 2 {
+                    timer.start(ms(1), zephyr::K_NO_WAIT);
+                }
+            })
+        }
+    };
+    timer.start::<C>(ms(1), zephyr::K_NO_WAIT);
+    wait_for(&expired, 3);
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(expired.load(Ordering::SeqCst), 3);
+}
+
+fn timer_stream() {
+    let mut executor = unsafe { Executor::new(&EXECUTOR_MUTEX, &EXECUTOR_SIGNAL) };
+    executor
+        .spawn_local(async {
+            let stream = unsafe { TimerStream::new(TEST_TIMER.kobj(), &TIMER_SIGNAL) };
+            stream.timer().start::<C>(ms(1), ms(1));
+            let mut total = 0;
+            let mut stream = stream.take(3);
+            while let Some(count) = stream.next().await {
+                assert!(count > 0);
+                total += count;
+            }
+            assert!(total >= 3);
+        })
+        .unwrap();
+    executor.run::<C>();
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    plain_timer();
+    callback_timer();
+    restart_from_handler();
+    timer_stream();
+}
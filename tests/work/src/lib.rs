@@ -0,0 +1,188 @@
+extern crate zephyr;
+extern crate zephyr_macros;
+extern crate zephyr_sys;
+
+use std::alloc::{alloc, dealloc, Layout};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use zephyr::context::Kernel as C;
+use zephyr::work::{KWorkQ, Submitted, WorkQueue};
+use zephyr::{Error, Timeout};
+use zephyr_sys::raw::k_thread_stack_t;
+
+extern "C" {
+    static rust_work_q: KWorkQ;
+    static mut rust_work_q_stack: k_thread_stack_t;
+    static rust_work_q_stack_size: usize;
+}
+
+static PLAIN_COUNT: AtomicU32 = AtomicU32::new(0);
+static DELAYED_COUNT: AtomicU32 = AtomicU32::new(0);
+
+fn plain_handler() {
+    PLAIN_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+fn delayed_handler() {
+    DELAYED_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+zephyr_macros::k_work_define!(PLAIN_WORK, plain_handler);
+zephyr_macros::k_work_delayable_define!(DELAYED_WORK, delayed_handler);
+
+fn ms(ms: u64) -> Timeout {
+    (&Duration::from_millis(ms)).into()
+}
+
+fn static_work(queue: WorkQueue<'static>) {
+    assert_eq!(PLAIN_WORK.submit::<C>(), Ok(Submitted::Queued));
+    PLAIN_WORK.flush::<C>();
+    assert!(!PLAIN_WORK.is_pending::<C>());
+    assert_eq!(PLAIN_COUNT.load(Ordering::SeqCst), 1);
+
+    assert_eq!(
+        PLAIN_WORK.submit_to_queue::<C>(queue),
+        Ok(Submitted::Queued)
+    );
+    PLAIN_WORK.flush::<C>();
+    assert_eq!(PLAIN_COUNT.load(Ordering::SeqCst), 2);
+
+    // Scheduling again while pending changes nothing. Rescheduling replaces the delay.
+    assert_eq!(
+        DELAYED_WORK.schedule::<C>(queue, ms(1000)),
+        Ok(Submitted::Queued)
+    );
+    assert_eq!(
+        DELAYED_WORK.schedule::<C>(queue, ms(1000)),
+        Ok(Submitted::AlreadyPending)
+    );
+    assert!(DELAYED_WORK.is_pending::<C>());
+    assert_eq!(
+        DELAYED_WORK.reschedule::<C>(queue, ms(1)),
+        Ok(Submitted::Queued)
+    );
+    DELAYED_WORK.flush::<C>();
+    assert_eq!(DELAYED_COUNT.load(Ordering::SeqCst), 1);
+
+    assert_eq!(
+        DELAYED_WORK.schedule::<C>(queue, ms(1000)),
+        Ok(Submitted::Queued)
+    );
+    assert!(DELAYED_WORK.cancel::<C>());
+    assert!(!DELAYED_WORK.is_pending::<C>());
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(DELAYED_COUNT.load(Ordering::SeqCst), 1);
+}
+
+fn closures(queue: WorkQueue<'static>) {
+    let count = Arc::new(AtomicU32::new(0));
+
+    let handle = {
+        let count = count.clone();
+        queue
+            .submit::<C, _>(move || {
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("submit")
+    };
+    assert_eq!(handle.submitted(), Submitted::Queued);
+    handle.flush();
+    assert!(handle.is_done());
+    assert!(!handle.cancel());
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+    drop(handle);
+
+    // A canceled closure never runs and is dropped along with its captures
+    let handle = {
+        let count = count.clone();
+        queue
+            .schedule::<C, _>(ms(1000), move || {
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("schedule")
+    };
+    assert!(handle.is_pending());
+    assert!(!handle.is_done());
+    assert!(handle.cancel());
+    assert!(handle.is_done());
+    assert_eq!(handle.reschedule(ms(1)).err(), Some(zephyr::Error::Already));
+    drop(handle);
+    assert_eq!(Arc::strong_count(&count), 1);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    // A rescheduled closure runs once
+    let handle = {
+        let count = count.clone();
+        queue
+            .schedule::<C, _>(ms(1000), move || {
+                count.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("schedule")
+    };
+    handle.reschedule(ms(1)).expect("reschedule");
+    handle.flush();
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+/// Drop each handle while the kernel still has the item queued, so every release is deferred.
+/// The heap is small enough that items never reclaimed would exhaust it.
+fn reclaim(queue: WorkQueue<'static>) {
+    let count = Arc::new(AtomicU32::new(0));
+    for i in 1..=200 {
+        let c = count.clone();
+        drop(
+            queue
+                .submit::<C, _>(move || {
+                    c.fetch_add(1, Ordering::SeqCst);
+                })
+                .expect("submit"),
+        );
+        while count.load(Ordering::SeqCst) < i {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+/// Submitting with the heap exhausted fails instead of aborting
+fn no_memory(queue: WorkQueue<'static>) {
+    // Fill the heap with blocks too small for a work item, linked through their first word
+    let layout = Layout::new::<*mut u8>();
+    let mut head: *mut u8 = std::ptr::null_mut();
+    loop {
+        let block = unsafe { alloc(layout) };
+        if block.is_null() {
+            break;
+        }
+        unsafe { *(block as *mut *mut u8) = head };
+        head = block;
+    }
+
+    let result = queue.submit::<C, _>(|| ());
+
+    while !head.is_null() {
+        let next = unsafe { *(head as *mut *mut u8) };
+        unsafe { dealloc(head, layout) };
+        head = next;
+    }
+    assert_eq!(result.err(), Some(Error::NoMemory));
+}
+
+#[no_mangle]
+pub extern "C" fn rust_test_main() {
+    let queue = unsafe {
+        WorkQueue::start::<C>(
+            &rust_work_q,
+            core::ptr::addr_of_mut!(rust_work_q_stack),
+            rust_work_q_stack_size,
+            5,
+        )
+    };
+
+    static_work(queue);
+    closures(WorkQueue::system());
+    closures(queue);
+    reclaim(queue);
+    no_memory(queue);
+}
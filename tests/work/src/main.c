@@ -0,0 +1,12 @@
+#include <zephyr.h>
+
+extern void rust_test_main(void);
+
+K_KERNEL_STACK_DEFINE(rust_work_q_stack, 1024);
+const size_t rust_work_q_stack_size = K_KERNEL_STACK_SIZEOF(rust_work_q_stack);
+struct k_work_q rust_work_q;
+
+void test_main(void)
+{
+    rust_test_main();
+}